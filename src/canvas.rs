@@ -1,17 +1,34 @@
 //! A painting surface.
 
-use crate::{gpu::Shader, paint::Paint, Angle, Element, Method, P2, V2};
+use crate::{
+    gpu::{BlendMode, Shader},
+    paint::Paint,
+    raster::{Stroke, DEFAULT_TOLERANCE, MIN_TOLERANCE},
+    Angle, Element, Method, P2, V2,
+};
+use euclid::default::Rect;
 use lyon_path::Builder;
+use lyon_tessellation::{FillRule, LineCap, LineJoin, StrokeOptions};
 use palette::{Alpha, IntoColor, LinSrgb, LinSrgba};
 
 /// A painting surface.
 pub struct Canvas {
     path: Builder,
     shader: Shader,
+    blend_mode: BlendMode,
     color: LinSrgba,
+    opacity: f32,
     stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    auto_close: bool,
+    fill_rule: FillRule,
+    tolerance: f32,
     scale: f32,
-    elements: Vec<Element>,
+    z_index: i32,
+    scissor: Option<Rect<f32>>,
+    elements: Vec<(i32, Element)>,
 }
 
 impl Canvas {
@@ -19,9 +36,19 @@ impl Canvas {
         Self {
             path: Builder::new(),
             shader: default_shader,
+            blend_mode: BlendMode::Normal,
             color: Alpha::<LinSrgb, _>::new(1., 1., 1., 1.),
+            opacity: 1.,
             scale,
             stroke_width: 1.,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+            auto_close: false,
+            fill_rule: FillRule::EvenOdd,
+            tolerance: DEFAULT_TOLERANCE,
+            z_index: 0,
+            scissor: None,
             elements: vec![],
         }
     }
@@ -31,6 +58,11 @@ impl Canvas {
         element.paint(self);
     }
 
+    /// Paints each element in order, so the first is painted first.
+    pub fn paint_all<P: Paint>(&mut self, elements: impl IntoIterator<Item = P>) {
+        elements.into_iter().for_each(|element| self.paint(element));
+    }
+
     /// Sets the current color.
     pub fn set_color(&mut self, color: impl IntoColor) {
         self.color = Alpha::from(color.into_rgb());
@@ -44,9 +76,17 @@ impl Canvas {
         };
     }
 
-    /// Stats a new path at the given point.
+    /// Sets the opacity applied to everything painted after this call, in `[0, 1]`. It scales
+    /// the alpha of the current color, so translucent colors become more translucent.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.).min(1.);
+    }
+
+    /// Starts a new subpath of the current path at the given point.
+    ///
+    /// Subpaths are painted together by the next `fill()` or `stroke()`, so a fill can have holes
+    /// according to the fill rule.
     pub fn move_to(&mut self, dest: P2) {
-        self.path = Builder::new();
         self.path.move_to(dest * self.scale);
     }
 
@@ -83,14 +123,62 @@ impl Canvas {
         self.stroke_width = stroke_width * self.scale;
     }
 
+    /// Sets the shape drawn by `stroke()` at the ends of open paths.
+    pub fn set_line_cap(&mut self, line_cap: LineCap) {
+        self.line_cap = line_cap;
+    }
+
+    /// Sets the shape drawn by `stroke()` where two segments of the path meet.
+    pub fn set_line_join(&mut self, line_join: LineJoin) {
+        self.line_join = line_join;
+    }
+
+    /// Sets the limit on the ratio of miter length to stroke width for miter joins. Joins which
+    /// would exceed it are drawn as bevels.
+    ///
+    /// The limit must be at least 1, since no miter is shorter than the stroke is wide; smaller
+    /// values are raised to 1.
+    pub fn set_miter_limit(&mut self, miter_limit: f32) {
+        self.miter_limit = miter_limit.max(1.);
+    }
+
+    /// Sets whether `stroke()` closes open subpaths which end within the tolerance of where they
+    /// start, joining their ends with the line join instead of drawing two caps. The closing
+    /// edge replaces a final line, and a final curve is bent to end at the start. The default is
+    /// false, which strokes every open subpath open.
+    pub fn set_auto_close(&mut self, auto_close: bool) {
+        self.auto_close = auto_close;
+    }
+
+    /// Sets the rule used by `fill()` to decide which regions of the path are inside.
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        self.fill_rule = fill_rule;
+    }
+
+    /// Sets the maximum distance, in output pixels, between a curve and the line segments used
+    /// to raster it. Smaller values give smoother curves at the cost of more geometry.
+    ///
+    /// The tolerance must be positive and finite, since curves are flattened in steps which
+    /// shrink with it. Values below 0.001 pixels, including NaN, are raised to 0.001, and
+    /// infinity is lowered to the largest finite value.
+    pub fn set_tolerance(&mut self, tolerance: f32) {
+        self.tolerance = tolerance.max(MIN_TOLERANCE).min(std::f32::MAX);
+    }
+
     /// Paints the current path by filling the region inside the path.
     pub fn fill(&mut self) {
-        self.push_element(Method::Fill);
+        self.push_element(Method::Fill(self.fill_rule));
     }
 
     /// Paints the current path by stroking the path.
     pub fn stroke(&mut self) {
-        self.push_element(Method::Stroke(self.stroke_width));
+        self.push_element(Method::Stroke(Stroke {
+            width: self.stroke_width,
+            line_cap: self.line_cap,
+            line_join: self.line_join,
+            miter_limit: self.miter_limit,
+            auto_close: self.auto_close,
+        }));
     }
 
     /// Sets the current shader used to shade rastered paths.
@@ -102,16 +190,51 @@ impl Canvas {
         self.shader = shader;
     }
 
+    /// Restricts elements painted after this call to the given rectangle of the canvas, so that
+    /// nothing outside it is drawn. `None`, the default, allows drawing everywhere.
+    ///
+    /// Changing the rectangle ends the current draw call, as changing shaders does.
+    pub fn set_scissor(&mut self, scissor: Option<Rect<f32>>) {
+        self.scissor = scissor.map(|scissor| scissor.scale(self.scale, self.scale));
+    }
+
+    /// Sets the z index of elements painted after this call. Elements with a higher z index are
+    /// drawn over those with a lower one, and elements with the same z index are drawn in the
+    /// order they were painted. The default is 0.
+    pub fn set_z_index(&mut self, z_index: i32) {
+        self.z_index = z_index;
+    }
+
+    /// Sets the blend mode used to combine rastered paths with the canvas.
+    ///
+    /// Like changing shaders, changing blend modes requires a new draw call to the GPU.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
     fn push_element(&mut self, raster_method: Method) {
         let mut path = Builder::new();
         std::mem::swap(&mut self.path, &mut path);
 
-        self.elements.push(Element {
-            path,
-            color: self.color,
-            shader: self.shader.clone(),
-            raster_method,
-        });
+        if self.opacity == 0. {
+            return;
+        }
+
+        let mut color = self.color;
+        color.alpha *= self.opacity;
+
+        self.elements.push((
+            self.z_index,
+            Element {
+                path: path.build(),
+                color,
+                shader: self.shader.clone(),
+                raster_method,
+                blend_mode: self.blend_mode,
+                tolerance: self.tolerance,
+                scissor: self.scissor,
+            },
+        ));
     }
 }
 
@@ -119,7 +242,13 @@ impl IntoIterator for Canvas {
     type Item = Element;
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.elements.into_iter()
+    fn into_iter(mut self) -> Self::IntoIter {
+        // Sorting is stable, so elements at the same z index keep their painting order.
+        self.elements.sort_by_key(|(z_index, _)| *z_index);
+        self.elements
+            .into_iter()
+            .map(|(_, element)| element)
+            .collect::<Vec<_>>()
+            .into_iter()
     }
 }
@@ -1,7 +1,8 @@
 //! Forms.
 
+mod boolean;
 mod ellipse;
 mod ngon;
 mod polygon;
 
-pub use self::{ellipse::*, ngon::*, polygon::*};
+pub use self::{boolean::BooleanError, ellipse::*, ngon::*, polygon::*};
@@ -0,0 +1,463 @@
+//! Boolean operations on polygons.
+
+use crate::{path::signed_area, Polygon, P2};
+use failure::Fail;
+
+/// An error describing polygons which cannot be combined.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum BooleanError {
+    /// The outlines touch without crossing cleanly, as when polygons share an edge or a corner
+    /// meets the other's side. Moving one polygon by a tiny amount resolves this.
+    #[fail(display = "Vertex {:?} lies on the outline of the other polygon.", _0)]
+    VertexOnOutline(P2),
+}
+
+impl Polygon {
+    /// Returns the polygons covering the area inside either polygon.
+    pub fn union(&self, other: &Polygon) -> Result<Vec<Polygon>, BooleanError> {
+        combine(self, other, Operation::Union)
+    }
+
+    /// Returns the polygons covering the area inside both polygons.
+    pub fn intersection(&self, other: &Polygon) -> Result<Vec<Polygon>, BooleanError> {
+        combine(self, other, Operation::Intersection)
+    }
+
+    /// Returns the polygons covering the area inside this polygon but outside the other.
+    ///
+    /// If the other polygon lies entirely inside this one, the result is this polygon followed
+    /// by the other wound in the opposite direction, which fills as a hole under either fill rule.
+    pub fn difference(&self, other: &Polygon) -> Result<Vec<Polygon>, BooleanError> {
+        combine(self, other, Operation::Difference)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// A vertex in the linked outline of one polygon, which may be a crossing with the other.
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    point: P2,
+    /// The index of the crossing this node marks, if any.
+    crossing: Option<usize>,
+    /// Whether walking forward from this crossing stays on the side of the other polygon which
+    /// belongs in the result.
+    forward: bool,
+}
+
+/// Clips two simple polygons with the Greiner-Hormann algorithm.
+///
+/// Result rings inside an odd number of other result rings are holes, and are wound opposite
+/// to the subject so they stay empty under either fill rule.
+///
+/// The algorithm needs every crossing of the outlines to be a clean crossing of two edges, so
+/// inputs where a vertex lies exactly on the other's outline, including overlapping edges, are
+/// rejected.
+fn combine(
+    subject: &Polygon,
+    clip: &Polygon,
+    operation: Operation,
+) -> Result<Vec<Polygon>, BooleanError> {
+    let subject_vertices = subject.vertices().collect::<Vec<_>>();
+    let clip_vertices = clip.vertices().collect::<Vec<_>>();
+    if subject_vertices.len() < 3 || clip_vertices.len() < 3 {
+        return Ok(match operation {
+            Operation::Intersection => vec![],
+            Operation::Union => vec![subject.clone(), clip.clone()]
+                .into_iter()
+                .filter(|p| p.vertices().count() >= 3)
+                .collect(),
+            Operation::Difference => vec![subject.clone()],
+        });
+    }
+
+    let touching = subject_vertices
+        .iter()
+        .find(|v| on_outline(&clip_vertices, **v))
+        .or_else(|| {
+            clip_vertices
+                .iter()
+                .find(|v| on_outline(&subject_vertices, **v))
+        });
+    if let Some(vertex) = touching {
+        return Err(BooleanError::VertexOnOutline(*vertex));
+    }
+
+    let (mut subject_nodes, mut clip_nodes, crossings) =
+        link_crossings(&subject_vertices, &clip_vertices);
+    if crossings.is_empty() {
+        return Ok(combine_disjoint(subject, clip, operation));
+    }
+
+    // Intersections keep the parts of each outline inside the other, unions keep the parts
+    // outside, and differences keep the subject outside the clip and the clip inside the subject.
+    let (subject_inside, clip_inside) = match operation {
+        Operation::Intersection => (true, true),
+        Operation::Union => (false, false),
+        Operation::Difference => (false, true),
+    };
+    mark_directions(&mut subject_nodes, &clip_vertices, subject_inside);
+    mark_directions(&mut clip_nodes, &subject_vertices, clip_inside);
+
+    let orientation = signed_area(&subject_vertices).signum();
+    let mut visited = vec![false; crossings.len()];
+    let mut rings = vec![];
+    for start in 0..crossings.len() {
+        if visited[start] {
+            continue;
+        }
+
+        let mut ring = vec![];
+        let (mut on_subject, mut index) = (true, crossings[start].0);
+        loop {
+            let nodes = if on_subject {
+                &subject_nodes
+            } else {
+                &clip_nodes
+            };
+            let forward = nodes[index].forward;
+            ring.push(nodes[index].point);
+            if let Some(crossing) = nodes[index].crossing {
+                visited[crossing] = true;
+            }
+
+            let crossing = loop {
+                index = if forward {
+                    (index + 1) % nodes.len()
+                } else {
+                    (index + nodes.len() - 1) % nodes.len()
+                };
+                match nodes[index].crossing {
+                    Some(crossing) => break crossing,
+                    None => ring.push(nodes[index].point),
+                }
+            };
+
+            if crossing == start {
+                break;
+            }
+            on_subject = !on_subject;
+            index = if on_subject {
+                crossings[crossing].0
+            } else {
+                crossings[crossing].1
+            };
+        }
+
+        rings.push(ring);
+    }
+
+    let holes = rings
+        .iter()
+        .enumerate()
+        .map(|(i, ring)| {
+            let depth = rings
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| *j != i && contains(other, ring[0]))
+                .count();
+            depth % 2 == 1
+        })
+        .collect::<Vec<_>>();
+
+    Ok(rings
+        .into_iter()
+        .zip(holes)
+        .map(|(mut ring, hole)| {
+            if (signed_area(&ring).signum() == orientation) == hole {
+                ring.reverse();
+            }
+            ring.into_iter().collect()
+        })
+        .collect())
+}
+
+/// Returns the outlines of both polygons with their crossings inserted in order along each
+/// edge, and the index of each crossing in the subject and clip outlines.
+fn link_crossings(subject: &[P2], clip: &[P2]) -> (Vec<Node>, Vec<Node>, Vec<(usize, usize)>) {
+    let edges = |vertices: &[P2]| {
+        (0..vertices.len())
+            .map(|i| (vertices[i], vertices[(i + 1) % vertices.len()]))
+            .collect::<Vec<_>>()
+    };
+    let (subject_edges, clip_edges) = (edges(subject), edges(clip));
+
+    // Each crossing as (subject edge, position along it, clip edge, position along it, point).
+    let mut found = vec![];
+    for (i, (a0, a1)) in subject_edges.iter().enumerate() {
+        for (j, (b0, b1)) in clip_edges.iter().enumerate() {
+            if let Some((s, t)) = segment_intersection(*a0, *a1, *b0, *b1) {
+                found.push((i, s, j, t, a0.lerp(*a1, s)));
+            }
+        }
+    }
+
+    let outline = |vertices: &[P2], position: &dyn Fn(usize) -> (usize, f32)| {
+        let mut nodes = vec![];
+        let mut indices = vec![0; found.len()];
+        for (edge, vertex) in vertices.iter().enumerate() {
+            nodes.push(Node {
+                point: *vertex,
+                crossing: None,
+                forward: true,
+            });
+
+            let mut on_edge = (0..found.len())
+                .filter(|k| position(*k).0 == edge)
+                .collect::<Vec<_>>();
+            on_edge.sort_by(|a, b| {
+                position(*a)
+                    .1
+                    .partial_cmp(&position(*b).1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            for k in on_edge {
+                indices[k] = nodes.len();
+                nodes.push(Node {
+                    point: found[k].4,
+                    crossing: Some(k),
+                    forward: true,
+                });
+            }
+        }
+        (nodes, indices)
+    };
+
+    let (subject_nodes, subject_indices) = outline(subject, &|k| (found[k].0, found[k].1));
+    let (clip_nodes, clip_indices) = outline(clip, &|k| (found[k].2, found[k].3));
+    let crossings = subject_indices.into_iter().zip(clip_indices).collect();
+
+    (subject_nodes, clip_nodes, crossings)
+}
+
+/// Marks whether to walk forward from each crossing in the outline to stay inside (or outside)
+/// the other polygon. Each crossing toggles the side of the other polygon the outline is on.
+fn mark_directions(nodes: &mut [Node], other: &[P2], keep_inside: bool) {
+    let mut inside = contains(other, nodes[0].point);
+    for node in nodes.iter_mut().filter(|node| node.crossing.is_some()) {
+        inside = !inside;
+        node.forward = inside == keep_inside;
+    }
+}
+
+fn combine_disjoint(subject: &Polygon, clip: &Polygon, operation: Operation) -> Vec<Polygon> {
+    let subject_vertices = subject.vertices().collect::<Vec<_>>();
+    let clip_vertices = clip.vertices().collect::<Vec<_>>();
+    let subject_in_clip = contains(&clip_vertices, subject_vertices[0]);
+    let clip_in_subject = contains(&subject_vertices, clip_vertices[0]);
+
+    match operation {
+        Operation::Intersection if subject_in_clip => vec![subject.clone()],
+        Operation::Intersection if clip_in_subject => vec![clip.clone()],
+        Operation::Intersection => vec![],
+        Operation::Union if subject_in_clip => vec![clip.clone()],
+        Operation::Union if clip_in_subject => vec![subject.clone()],
+        Operation::Union => vec![subject.clone(), clip.clone()],
+        Operation::Difference if subject_in_clip => vec![],
+        Operation::Difference if clip_in_subject => {
+            let mut hole = clip_vertices;
+            if signed_area(&hole).signum() == signed_area(&subject_vertices).signum() {
+                hole.reverse();
+            }
+            vec![subject.clone(), hole.into_iter().collect()]
+        }
+        Operation::Difference => vec![subject.clone()],
+    }
+}
+
+/// Returns the positions along each segment at which they cross, excluding their endpoints.
+fn segment_intersection(a0: P2, a1: P2, b0: P2, b1: P2) -> Option<(f32, f32)> {
+    let (da, db) = (a1 - a0, b1 - b0);
+    let denominator = da.cross(db);
+    if denominator == 0. {
+        return None;
+    }
+
+    let offset = b0 - a0;
+    let s = offset.cross(db) / denominator;
+    let t = offset.cross(da) / denominator;
+    if s > 0. && s < 1. && t > 0. && t < 1. {
+        Some((s, t))
+    } else {
+        None
+    }
+}
+
+/// Returns true if the point lies exactly on an edge of the polygon.
+fn on_outline(vertices: &[P2], p: P2) -> bool {
+    (0..vertices.len()).any(|i| {
+        let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+        (b - a).cross(p - a) == 0.
+            && p.x >= a.x.min(b.x)
+            && p.x <= a.x.max(b.x)
+            && p.y >= a.y.min(b.y)
+            && p.y <= a.y.max(b.y)
+    })
+}
+
+fn contains(vertices: &[P2], p: P2) -> bool {
+    let mut inside = false;
+    for i in 0..vertices.len() {
+        let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Path;
+    use lyon_tessellation::FillRule;
+
+    fn square(min: f32, max: f32) -> Polygon {
+        vec![
+            P2::new(min, min),
+            P2::new(max, min),
+            P2::new(max, max),
+            P2::new(min, max),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn area(polygon: &Polygon) -> f32 {
+        signed_area(&polygon.vertices().collect::<Vec<_>>())
+    }
+
+    fn sorted_vertices(polygon: &Polygon) -> Vec<(f32, f32)> {
+        let mut vertices = polygon.vertices().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        vertices.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        vertices
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let (a, b) = (square(0., 2.), square(1., 3.));
+
+        let intersection = a.intersection(&b).expect("to intersect squares");
+        assert_eq!(intersection.len(), 1);
+        assert_eq!(
+            sorted_vertices(&intersection[0]),
+            vec![(1., 1.), (1., 2.), (2., 1.), (2., 2.)]
+        );
+        assert_eq!(area(&intersection[0]), 1.);
+
+        let difference = a.difference(&b).expect("to subtract squares");
+        assert_eq!(difference.len(), 1);
+        assert_eq!(
+            sorted_vertices(&difference[0]),
+            vec![(0., 0.), (0., 2.), (1., 1.), (1., 2.), (2., 0.), (2., 1.)]
+        );
+        assert_eq!(area(&difference[0]), 3.);
+
+        let union = a.union(&b).expect("to unite squares");
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].vertices().count(), 8);
+        assert_eq!(area(&union[0]), 7.);
+    }
+
+    #[test]
+    fn disjoint_and_contained() {
+        let (outer, inner, apart) = (square(0., 4.), square(1., 2.), square(5., 6.));
+
+        assert_eq!(outer.intersection(&apart), Ok(vec![]));
+        assert_eq!(outer.union(&apart), Ok(vec![outer.clone(), apart.clone()]));
+        assert_eq!(outer.intersection(&inner), Ok(vec![inner.clone()]));
+        assert_eq!(outer.union(&inner), Ok(vec![outer.clone()]));
+        assert_eq!(inner.difference(&outer), Ok(vec![]));
+
+        let holed = outer.difference(&inner).expect("to subtract squares");
+        assert_eq!(holed.len(), 2);
+        assert_eq!(area(&holed[0]) + area(&holed[1]), 15.);
+    }
+
+    #[test]
+    fn union_with_hole() {
+        let cup: Polygon = vec![
+            P2::new(0., 0.),
+            P2::new(6., 0.),
+            P2::new(6., 4.),
+            P2::new(4., 4.),
+            P2::new(4., 2.),
+            P2::new(2., 2.),
+            P2::new(2., 4.),
+            P2::new(0., 4.),
+        ]
+        .into_iter()
+        .collect();
+        let cap: Polygon = vec![
+            P2::new(-1., 7.),
+            P2::new(-1., 3.),
+            P2::new(1., 3.),
+            P2::new(1., 5.),
+            P2::new(5., 5.),
+            P2::new(5., 3.),
+            P2::new(7., 3.),
+            P2::new(7., 7.),
+        ]
+        .into_iter()
+        .collect();
+
+        let union = cup.union(&cap).expect("to unite cup and cap");
+        assert_eq!(union.len(), 2);
+        let (outer, hole) = if area(&union[0]).abs() > area(&union[1]).abs() {
+            (&union[0], &union[1])
+        } else {
+            (&union[1], &union[0])
+        };
+        assert_eq!(area(outer), 50.);
+        assert_eq!(area(hole), -8.);
+
+        let mut path = Path::new();
+        for ring in &union {
+            let mut vertices = ring.vertices();
+            path.move_to(vertices.next().unwrap());
+            vertices.for_each(|v| path.line_to(v));
+            path.close_path();
+        }
+        assert!(!path.contains(P2::new(3., 3.), FillRule::NonZero));
+        assert!(path.contains(P2::new(1., 1.), FillRule::NonZero));
+        assert!(path.contains(P2::new(3., 6.), FillRule::NonZero));
+    }
+
+    #[test]
+    fn touching_outlines() {
+        let a = square(0., 2.);
+        let polygon = |vertices: &[(f32, f32)]| -> Polygon {
+            vertices.iter().map(|(x, y)| P2::new(*x, *y)).collect()
+        };
+
+        // The triangle's apex touches the square's top edge.
+        let apex = polygon(&[(1., 2.), (3., -1.), (-1., -1.)]);
+        // The rectangle shares part of the square's bottom edge.
+        let shelf = polygon(&[(1., 0.), (3., 0.), (3., 1.), (1., 1.)]);
+        // The squares share a whole edge.
+        let beside = polygon(&[(2., 0.), (4., 0.), (4., 2.), (2., 2.)]);
+
+        for other in &[apex, shelf, beside] {
+            for result in &[a.union(other), a.intersection(other), a.difference(other)] {
+                assert!(
+                    result.is_err(),
+                    "{:?} combined with {:?} gave {:?}",
+                    a,
+                    other,
+                    result
+                );
+            }
+        }
+
+        // Nudged off the edge, the apex crosses cleanly.
+        let nudged = polygon(&[(1., 2.001), (3., -1.), (-1., -1.)]);
+        let union = a.union(&nudged).expect("to unite nudged triangle");
+        assert_eq!(union.len(), 1);
+        assert!(area(&union[0]) > 6.);
+    }
+}
@@ -1,7 +1,8 @@
 //! Polygon.
 
-use crate::{Angle, Canvas, FlatIterPath, Paint, Rotate, Subdivide, Translate, P2, V2};
+use crate::{Angle, Canvas, FlatIterPath, Paint, Path, Rotate, Subdivide, Translate, P2, V2};
 use arrayvec::ArrayVec;
+use failure::Fail;
 use itertools::Itertools;
 use std::iter::{DoubleEndedIterator, FromIterator};
 
@@ -10,7 +11,36 @@ pub struct Polygon {
     vertices: Vec<P2>,
 }
 
+/// An error encountered while converting a path into polygons.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum PolygonError {
+    #[fail(
+        display = "Subpath {} has {} vertices; a polygon needs at least 3.",
+        _0, _1
+    )]
+    TooFewVertices(usize, usize),
+    #[fail(display = "Tolerance {} is not positive and finite.", _0)]
+    InvalidTolerance(f32),
+}
+
 impl Polygon {
+    /// Converts each subpath of a path into a polygon, flattening curves into edges which stray
+    /// at most `tolerance` from the curve. The tolerance must be positive and finite.
+    pub fn from_path(path: &Path, tolerance: f32) -> Result<Vec<Self>, PolygonError> {
+        if !(tolerance > 0. && tolerance.is_finite()) {
+            return Err(PolygonError::InvalidTolerance(tolerance));
+        }
+
+        path.flattened_subpaths(tolerance)
+            .into_iter()
+            .enumerate()
+            .map(|(i, vertices)| match vertices.len() {
+                n if n < 3 => Err(PolygonError::TooFewVertices(i, n)),
+                _ => Ok(Self { vertices }),
+            })
+            .collect()
+    }
+
     /// Returns an iterator over the polygon's vertices.
     pub fn vertices<'a>(&'a self) -> impl DoubleEndedIterator<Item = P2> + Clone + 'a {
         self.vertices.iter().copied()
@@ -97,3 +127,41 @@ impl FromIterator<P2> for Polygon {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_path() {
+        let path = Path::from_svg("M0 0 Q1 2 2 0 Z M3 0 H4 V1 Z").expect("to parse path");
+        let vertex_counts = |tolerance| {
+            Polygon::from_path(&path, tolerance)
+                .expect("to flatten path")
+                .iter()
+                .map(|polygon| polygon.vertices().count())
+                .collect::<Vec<_>>()
+        };
+
+        let coarse = vertex_counts(0.1);
+        let fine = vertex_counts(0.001);
+        assert_eq!(coarse.len(), 2);
+        assert!(fine[0] > coarse[0]);
+        assert_eq!(fine[1], 3);
+        assert_eq!(coarse[1], 3);
+
+        let line = Path::from_svg("M0 0 L1 1 M2 2 L3 3 L4 2").expect("to parse lines");
+        assert_eq!(
+            Polygon::from_path(&line, 0.1),
+            Err(PolygonError::TooFewVertices(0, 2))
+        );
+
+        for tolerance in &[0., -1., std::f32::INFINITY] {
+            assert_eq!(
+                Polygon::from_path(&path, *tolerance),
+                Err(PolygonError::InvalidTolerance(*tolerance))
+            );
+        }
+        assert!(Polygon::from_path(&path, std::f32::NAN).is_err());
+    }
+}
@@ -1,10 +1,12 @@
 //! GPU handle and types.
 
 use crate::{
-    raster::{raster_path, Method},
+    raster::{raster_bounds, raster_paths, Method},
+    shaders::ShaderError,
     uniforms::*,
-    Result,
+    Result, V2, V3,
 };
+use euclid::default::{Point2D, Rect, Size2D};
 use glium::{
     backend::{
         glutin::{headless::Headless, Display},
@@ -13,6 +15,7 @@ use glium::{
     glutin::EventsLoop,
     implement_vertex,
     index::PrimitiveType,
+    program::{ProgramCreationError, ShaderType},
     texture::{
         texture2d::Texture2d, texture2d_multisample::Texture2dMultisample, MipmapsOption,
         RawImage2d, UncompressedFloatFormat,
@@ -23,10 +26,10 @@ use glium::{
 };
 use glutin::dpi::PhysicalSize;
 use itertools::Itertools;
-use lyon_path::Builder;
+use lyon_path::Path;
 use palette::LinSrgba;
 use rand::random;
-use std::rc::Rc;
+use std::{fmt, rc::Rc, time::Duration};
 
 #[derive(Debug, Copy, Clone)]
 pub struct GpuVertex {
@@ -36,6 +39,9 @@ pub struct GpuVertex {
 
 implement_vertex!(GpuVertex, vpos, vcol);
 
+/// The names of the vertex attributes available to vertex shaders.
+const VERTEX_ATTRIBUTES: [&str; 2] = ["vpos", "vcol"];
+
 pub(crate) const TEXTURE_FORMAT: UncompressedFloatFormat = UncompressedFloatFormat::F32F32F32F32;
 
 pub const VERTEX_SHADER: &str = include_str!("shaders/default.vert");
@@ -54,11 +60,76 @@ pub struct UniformBuffer {
 }
 
 impl UniformBuffer {
+    /// Returns a builder for a buffer of plain uniform values.
+    pub fn builder() -> UniformBufferBuilder {
+        UniformBufferBuilder::default()
+    }
+
     pub fn push(&mut self, name: String, value: UniformValue<'static>) {
         self.uniforms.push((name, value));
     }
 }
 
+/// Lists the names of the uniforms in the buffer, in the order they are bound. Values are left
+/// out because they may be large arrays or textures.
+impl fmt::Debug for UniformBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = vec![];
+        self.visit_values(|name, _| names.push(name.to_string()));
+        f.debug_struct("UniformBuffer")
+            .field("uniforms", &names)
+            .finish()
+    }
+}
+
+/// Builds a `UniformBuffer` one typed value at a time.
+#[derive(Default)]
+pub struct UniformBufferBuilder {
+    buffer: UniformBuffer,
+}
+
+impl UniformBufferBuilder {
+    /// Sets a `float` uniform.
+    pub fn float(self, name: impl Into<String>, value: f32) -> Self {
+        self.push(name, UniformValue::Float(value))
+    }
+
+    /// Sets a `vec2` uniform.
+    pub fn vec2(self, name: impl Into<String>, value: V2) -> Self {
+        self.push(name, UniformValue::Vec2(value.to_array()))
+    }
+
+    /// Sets a `vec3` uniform.
+    pub fn vec3(self, name: impl Into<String>, value: V3) -> Self {
+        self.push(name, UniformValue::Vec3(value.to_array()))
+    }
+
+    /// Sets a `vec4` uniform.
+    pub fn vec4(self, name: impl Into<String>, value: [f32; 4]) -> Self {
+        self.push(name, UniformValue::Vec4(value))
+    }
+
+    /// Sets an `int` uniform.
+    pub fn int(self, name: impl Into<String>, value: i32) -> Self {
+        self.push(name, UniformValue::SignedInt(value))
+    }
+
+    /// Sets a `mat4` uniform from columns.
+    pub fn mat4(self, name: impl Into<String>, value: [[f32; 4]; 4]) -> Self {
+        self.push(name, UniformValue::Mat4(value))
+    }
+
+    /// Returns the buffer of uniforms set so far.
+    pub fn build(self) -> UniformBuffer {
+        self.buffer
+    }
+
+    fn push(mut self, name: impl Into<String>, value: UniformValue<'static>) -> Self {
+        self.buffer.push(name.into(), value);
+        self
+    }
+}
+
 impl Uniforms for UniformBuffer {
     fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
         if let Some(user_uniforms) = self.user_uniforms.as_ref().map(Rc::as_ref) {
@@ -92,14 +163,105 @@ pub struct Shader {
     id: u64,
     program: Rc<Program>,
     uniforms: UniformBuffer,
+    /// Whether the program has its own vertex shader, which may draw paths anywhere rather than
+    /// where they lie in the viewport.
+    custom_vertex: bool,
+}
+
+/// Lists the uniforms the program declares alongside the names of those bound to it.
+impl fmt::Debug for Shader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let declared = self
+            .program
+            .uniforms()
+            .map(|(name, _)| name.as_str())
+            .sorted()
+            .collect::<Vec<_>>();
+        f.debug_struct("Shader")
+            .field("id", &self.id)
+            .field("declared_uniforms", &declared)
+            .field("bound_uniforms", &self.uniforms)
+            .field("custom_vertex", &self.custom_vertex)
+            .finish()
+    }
+}
+
+/// The way the colors of a rastered path are combined with the colors already on the canvas.
+///
+/// Blending uses the GPU's fixed function blend stage. `Multiply` and `Screen` are exact for
+/// opaque colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Paints over the canvas, mixing by alpha.
+    Normal,
+    /// Multiplies the canvas by the painted color, darkening it.
+    Multiply,
+    /// Multiplies the inverse of the canvas and the painted color, lightening the canvas.
+    Screen,
+    /// Adds the painted color to the canvas.
+    Add,
+    /// Subtracts the painted color from the canvas.
+    Subtract,
+}
+
+impl BlendMode {
+    fn blend(self) -> Blend {
+        use LinearBlendingFactor::*;
+
+        let color = match self {
+            BlendMode::Normal => BlendingFunction::Addition {
+                source: SourceAlpha,
+                destination: OneMinusSourceAlpha,
+            },
+            BlendMode::Multiply => BlendingFunction::Addition {
+                source: DestinationColor,
+                destination: OneMinusSourceAlpha,
+            },
+            BlendMode::Screen => BlendingFunction::Addition {
+                source: SourceAlpha,
+                destination: OneMinusSourceColor,
+            },
+            BlendMode::Add => BlendingFunction::Addition {
+                source: SourceAlpha,
+                destination: One,
+            },
+            BlendMode::Subtract => BlendingFunction::ReverseSubtraction {
+                source: SourceAlpha,
+                destination: One,
+            },
+        };
+
+        Blend {
+            color,
+            alpha: BlendingFunction::Addition {
+                source: One,
+                destination: OneMinusSourceAlpha,
+            },
+            constant_value: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
 }
 
 /// A rasterable element in a composition.
 pub struct Element {
-    pub path: Builder,
+    pub path: Path,
     pub color: LinSrgba,
     pub raster_method: Method,
     pub shader: Shader,
+    pub blend_mode: BlendMode,
+    pub tolerance: f32,
+    pub scissor: Option<Rect<f32>>,
+}
+
+impl Element {
+    /// Returns a rectangle containing everything the element rasters, or `None` if its path is
+    /// empty.
+    ///
+    /// Strokes extend past their path by half their width, and further at miter joins and
+    /// square caps.
+    pub fn bounds(&self) -> Option<Rect<f32>> {
+        raster_bounds(&self.path, self.raster_method)
+    }
 }
 
 pub struct DisplayFacade(Display);
@@ -138,6 +300,8 @@ struct GpuCommand<'a, S> {
     pub target: &'a mut S,
     pub program: &'a Program,
     pub uniforms: &'a UniformBuffer,
+    pub blend_mode: BlendMode,
+    pub scissor: Option<glium::Rect>,
 }
 
 impl Gpu {
@@ -214,38 +378,90 @@ impl Gpu {
             id: random(),
             program: self.program.clone(),
             uniforms: UniformBuffer::default(),
+            custom_vertex: false,
         }
     }
 
-    pub(crate) fn compile_glsl(&self, source: &str) -> Result<Rc<Program>> {
-        Ok(Rc::new(Program::from_source(
-            self.ctx.as_ref(),
-            VERTEX_SHADER,
-            source,
-            None,
-        )?))
+    /// Compiles a GLSL fragment shader from source and binds the given uniforms to it.
+    ///
+    /// Unlike a `ShaderProgram`, the source is compiled once and not watched for changes.
+    pub fn compile_shader(
+        &self,
+        glsl: &str,
+        uniforms: impl Into<UniformBuffer>,
+    ) -> std::result::Result<Shader, ShaderError> {
+        Ok(self.build_shader(
+            self.compile_glsl(glsl)?,
+            uniforms,
+            /*custom_vertex=*/ false,
+        ))
+    }
+
+    pub(crate) fn compile_glsl(
+        &self,
+        source: &str,
+    ) -> std::result::Result<Rc<Program>, ShaderError> {
+        let program = Program::from_source(self.ctx.as_ref(), VERTEX_SHADER, source, None)
+            .map_err(|e| program_error(VERTEX_SHADER, source, e))?;
+        Ok(Rc::new(program))
+    }
+
+    /// Compiles a fragment shader with a custom vertex shader, which may only take the vertex
+    /// attributes in `GpuVertex`.
+    pub(crate) fn compile_glsl_with_vertex_shader(
+        &self,
+        vertex: &str,
+        fragment: &str,
+    ) -> std::result::Result<Rc<Program>, ShaderError> {
+        let program = Program::from_source(self.ctx.as_ref(), vertex, fragment, None)
+            .map_err(|e| program_error(vertex, fragment, e))?;
+
+        let unknown = program
+            .attributes()
+            .map(|(name, _)| name)
+            .find(|name| !VERTEX_ATTRIBUTES.contains(&name.as_str()));
+        if let Some(name) = unknown {
+            return Err(ShaderError::from_log(
+                vertex,
+                format!(
+                    "Vertex shader input `{}` is not a vertex attribute; expected one of {:?}.",
+                    name, VERTEX_ATTRIBUTES
+                ),
+            ));
+        }
+
+        Ok(Rc::new(program))
     }
 
     pub(crate) fn build_shader(
         &self,
         program: Rc<Program>,
         uniforms: impl Into<UniformBuffer>,
+        custom_vertex: bool,
     ) -> Shader {
         Shader {
             id: random(),
             program,
             uniforms: uniforms.into(),
+            custom_vertex,
         }
     }
 
-    pub fn build_texture(&self, width: u32, height: u32) -> Result<Texture2dMultisample> {
+    /// Builds a texture to render into, which anti-aliases with the given number of samples
+    /// per pixel.
+    pub fn build_texture(
+        &self,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) -> Result<Texture2dMultisample> {
         Ok(Texture2dMultisample::empty_with_format(
             self.ctx.as_ref(),
             TEXTURE_FORMAT,
             MipmapsOption::NoMipmap,
             width,
             height,
-            /*samples=*/ 16,
+            samples,
         )?)
     }
 
@@ -281,15 +497,32 @@ impl Gpu {
         Ok(target.read())
     }
 
+    /// Renders the elements to the target.
+    ///
+    /// Every shader is given the `_valora_width` and `_valora_height` of the target in pixels and
+    /// the `_valora_time` of the frame in seconds. Shaders which do not declare them ignore them.
     pub fn render(
         &self,
         width: u32,
         height: u32,
+        time: Duration,
         elements: impl IntoIterator<Item = Element>,
         target: &mut impl Surface,
     ) -> Result<()> {
         let elements = elements.into_iter();
-        for (_id, batch) in &elements.group_by(|e| e.shader.id) {
+        let viewport = Rect::new(Point2D::origin(), Size2D::new(width as f32, height as f32));
+        let target_size = target.get_dimensions();
+        for ((_id, blend_mode, scissor), batch) in
+            &elements.group_by(|e| (e.shader.id, e.blend_mode, e.scissor))
+        {
+            let clip = match scissor {
+                Some(scissor) => match scissor.intersection(&viewport) {
+                    Some(clip) => Some(clip),
+                    None => continue,
+                },
+                None => None,
+            };
+
             let mut batch = batch.peekable();
             let mut first = if let Some(first) = batch.peek() {
                 first.shader.clone()
@@ -311,18 +544,30 @@ impl Gpu {
                 String::from("_valora_height_sign"),
                 UniformValue::Float(self.height_sign),
             );
+            first.uniforms.push(
+                String::from("_valora_time"),
+                UniformValue::Float(time.as_secs_f32()),
+            );
 
-            let (_, cpu_vertices, cpu_indices) = batch
-                .try_fold::<_, _, Result<(u32, Vec<GpuVertex>, Vec<u32>)>>(
-                    (0, vec![], vec![]),
-                    |(idx, mut vertices, mut indices), element| {
-                        let (mut new_vertices, new_indices) =
-                            raster_path(element.path, element.raster_method, element.color)?;
-                        vertices.append(&mut new_vertices);
-                        indices.extend(new_indices.into_iter().map(|i| i + idx));
-                        Ok((vertices.len() as u32, vertices, indices))
-                    },
-                )?;
+            let (cpu_vertices, cpu_indices) = raster_paths(
+                batch
+                    .map(|element| {
+                        (
+                            element.path,
+                            element.raster_method,
+                            element.color,
+                            element.tolerance,
+                        )
+                    })
+                    .collect(),
+                // Custom vertex shaders may move paths on screen from anywhere, so their paths
+                // cannot be culled.
+                if first.custom_vertex {
+                    None
+                } else {
+                    Some(clip.unwrap_or(viewport))
+                },
+            )?;
 
             let vertices = VertexBuffer::new(self.ctx.as_ref(), cpu_vertices.as_slice())?;
             let indices = IndexBuffer::new(
@@ -337,6 +582,9 @@ impl Gpu {
                 target,
                 program: first.program.as_ref(),
                 uniforms: &first.uniforms,
+                blend_mode,
+                scissor: clip
+                    .map(|clip| scissor_rect(clip, (width, height), target_size, self.height_sign)),
             })?;
         }
 
@@ -350,23 +598,151 @@ impl Gpu {
             cmd.program,
             cmd.uniforms,
             &DrawParameters {
-                blend: Blend {
-                    color: BlendingFunction::Addition {
-                        source: LinearBlendingFactor::SourceAlpha,
-                        destination: LinearBlendingFactor::OneMinusSourceAlpha,
-                    },
-                    alpha: BlendingFunction::Addition {
-                        source: LinearBlendingFactor::One,
-                        destination: LinearBlendingFactor::OneMinusSourceAlpha,
-                    },
-                    constant_value: (0.0, 0.0, 0.0, 0.0),
-                },
+                blend: cmd.blend_mode.blend(),
                 line_width: Some(1.0),
                 multisampling: true,
                 dithering: false,
                 smooth: Some(glium::draw_parameters::Smooth::Nicest),
+                scissor: cmd.scissor,
                 ..Default::default()
             },
         )?)
     }
 }
+
+/// Builds an error from a failed program, with context from the source of the stage which failed.
+/// Link errors belong to no one stage, so they are reported against the fragment shader.
+fn program_error(vertex: &str, fragment: &str, error: ProgramCreationError) -> ShaderError {
+    let source = match error {
+        ProgramCreationError::CompilationError(_, ShaderType::Vertex) => vertex,
+        _ => fragment,
+    };
+    ShaderError::from_log(source, error.to_string())
+}
+
+/// Converts a rectangle of the target, in the coordinates paths are drawn in, to the pixels of
+/// the target in the GL convention, which counts rows up from the bottom. Whether path
+/// coordinates count rows down from the top depends on the projection's height sign.
+///
+/// Paths are drawn in a space of `size`, which is stretched over the `target_size` pixels of
+/// the surface; on HiDPI windows the two differ.
+fn scissor_rect(
+    clip: Rect<f32>,
+    size: (u32, u32),
+    target_size: (u32, u32),
+    height_sign: f32,
+) -> glium::Rect {
+    let clip = clip.scale(
+        target_size.0 as f32 / size.0 as f32,
+        target_size.1 as f32 / size.1 as f32,
+    );
+    let height = target_size.1;
+    let (left, right) = (clip.min_x().floor(), clip.max_x().ceil());
+    let (top, bottom) = (clip.min_y().floor(), clip.max_y().ceil());
+    glium::Rect {
+        left: left as u32,
+        bottom: if height_sign > 0. {
+            height.saturating_sub(bottom as u32)
+        } else {
+            top as u32
+        },
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uniform_buffer_builder() {
+        let buffer = UniformBuffer::builder()
+            .float("a", 1.)
+            .float("b", 2.)
+            .vec2("c", V2::new(3., 4.))
+            .build();
+
+        let mut i = 0;
+        buffer.visit_values(|name, value| {
+            match (i, name, value) {
+                (0, "a", UniformValue::Float(v)) if v == 1. => {}
+                (1, "b", UniformValue::Float(v)) if v == 2. => {}
+                (2, "c", UniformValue::Vec2(v)) if v == [3., 4.] => {}
+                _ => panic!("unexpected uniform: {:?}", name),
+            }
+            i += 1;
+        });
+        assert_eq!(i, 3);
+    }
+
+    #[test]
+    fn program_error_uses_failing_stage() {
+        let vertex = "#version 330\nin vec2 vpos;\nvoid main() { gl_Position = vpos; }\n";
+        let fragment = "#version 330\nout vec4 frag;\nvoid main() { frag = vec4(1.0); }\n";
+        let log = String::from("0:3(28): error: value of type vec2 cannot be assigned");
+
+        let error = program_error(
+            vertex,
+            fragment,
+            ProgramCreationError::CompilationError(log.clone(), ShaderType::Vertex),
+        );
+        assert_eq!(error.line, Some(3));
+        assert!(error
+            .context
+            .contains(">    3 | void main() { gl_Position = vpos; }"));
+
+        let error = program_error(
+            vertex,
+            fragment,
+            ProgramCreationError::CompilationError(log, ShaderType::Fragment),
+        );
+        assert!(error
+            .context
+            .contains(">    3 | void main() { frag = vec4(1.0); }"));
+    }
+
+    #[test]
+    fn scissor_rect_rounds_outward_and_flips() {
+        let clip = Rect::new(Point2D::new(10.5, 20.), Size2D::new(30., 40.2));
+        assert_eq!(
+            scissor_rect(clip, (100, 100), (100, 100), 1.),
+            glium::Rect {
+                left: 10,
+                bottom: 39,
+                width: 31,
+                height: 41,
+            }
+        );
+        assert_eq!(scissor_rect(clip, (100, 100), (100, 100), -1.).bottom, 20);
+    }
+
+    #[test]
+    fn scissor_rect_scales_to_target_pixels() {
+        // A window with a DPI factor of 2 has twice as many pixels as the composition.
+        let clip = Rect::new(Point2D::new(10.5, 20.), Size2D::new(30., 40.2));
+        assert_eq!(
+            scissor_rect(clip, (100, 100), (200, 200), 1.),
+            glium::Rect {
+                left: 21,
+                bottom: 79,
+                width: 60,
+                height: 81,
+            }
+        );
+        assert_eq!(scissor_rect(clip, (100, 100), (200, 200), -1.).bottom, 40);
+    }
+
+    #[test]
+    fn uniform_buffer_debug() {
+        let buffer = UniformBuffer::builder()
+            .float("strength", 1.)
+            .vec2("offset", V2::new(3., 4.))
+            .build();
+
+        assert_eq!(
+            format!("{:?}", buffer),
+            r#"UniformBuffer { uniforms: ["strength", "offset"] }"#
+        );
+    }
+}
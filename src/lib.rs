@@ -20,6 +20,7 @@ pub mod prelude {
     };
     pub use super::*;
     pub use euclid::{self, Rect};
+    pub use lyon_tessellation::{FillRule, LineCap, LineJoin};
     pub use noise::{self, *};
     pub use noise_traits::*;
     pub use rayon::{self, prelude::*};
@@ -35,7 +36,7 @@ pub mod prelude {
 }
 
 pub use self::{
-    gpu::{Gpu, Shader},
+    gpu::{BlendMode, Gpu, Shader, UniformBuffer, UniformBufferBuilder},
     render::Context,
     shaders::ShaderProgram,
 };
@@ -94,6 +95,12 @@ pub struct Options {
     #[structopt(short = "d", long = "delay", default_value = "0")]
     pub delay: usize,
 
+    /// The number of samples per pixel used to anti-alias the painting. Higher counts give
+    /// smoother edges at the cost of memory and speed. Counts the GPU does not support fail when
+    /// the render texture is built.
+    #[structopt(long = "samples", default_value = "16")]
+    pub samples: u32,
+
     /// Prefix of output path. Output is <prefix>/<seed>/<frame_number>.png
     #[structopt(short = "o", long = "output", parse(from_os_str))]
     pub output: Option<PathBuf>,
@@ -197,7 +204,7 @@ where
 
     let (gpu, mut strategy) = if let Some(base_path) = options.output.clone() {
         let (gpu, _) = Gpu::new()?;
-        let buffer = gpu.build_texture(output_width, output_height)?;
+        let buffer = gpu.build_texture(output_width, output_height, options.samples)?;
 
         std::fs::create_dir_all(&base_path)
             .expect(&format!("To create save directory {}", base_path.display()));
@@ -221,7 +228,7 @@ where
     } else {
         let (gpu, events_loop, (screen_width, screen_height)) =
             Gpu::with_window(output_width, output_height)?;
-        let buffer = gpu.build_texture(screen_width, screen_height)?;
+        let buffer = gpu.build_texture(screen_width, screen_height, options.samples)?;
 
         let wait = Duration::from_secs_f64(1. / options.world.framerate as f64);
         let gpu_clone = gpu.clone();
@@ -1,7 +1,1334 @@
 //! Path types and tools.
 
-use crate::P2;
+use crate::{Angle, Canvas, Paint, Rotate, Scale, Translate, P2, V2};
+use arrayvec::ArrayVec;
+use euclid::default::{Rect, Transform2D};
+use failure::Fail;
+use lyon_geom::{
+    ArcFlags, BezierSegment, CubicBezierSegment, LineSegment, QuadraticBezierSegment, SvgArc,
+};
 use lyon_path::PathEvent;
+use lyon_tessellation::{FillRule, LineCap, LineJoin};
+
+/// The maximum distance between a curve and the line segments which approximate it when
+/// answering geometric queries.
+const FLATTEN_TOLERANCE: f32 = 0.001;
+
+/// A vector path of lines and curves, which may contain several subpaths.
+///
+/// Paths may be built with the same drawing commands as `Canvas`, or parsed from SVG path data.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    events: Vec<PathEvent>,
+}
+
+/// An error encountered while parsing SVG path data.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum ParseError {
+    #[fail(display = "Path data must begin with a move command.")]
+    MissingMoveTo,
+    #[fail(display = "Unsupported path command {:?} at offset {}.", _0, _1)]
+    UnsupportedCommand(char, usize),
+    #[fail(display = "Unexpected character {:?} at offset {}.", _0, _1)]
+    UnexpectedCharacter(char, usize),
+    #[fail(display = "Expected a number at offset {}.", _0)]
+    ExpectedNumber(usize),
+    #[fail(display = "Expected an arc flag of 0 or 1 at offset {}.", _0)]
+    ExpectedFlag(usize),
+}
+
+/// An error describing a path which cannot be drawn.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum PathError {
+    #[fail(display = "Path event {} has a coordinate which is not finite.", _0)]
+    NonFiniteCoordinate(usize),
+}
+
+/// A pattern of alternating dashes and gaps for `Path::dash`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashPattern {
+    /// The lengths of alternating dashes and gaps, starting with a dash. An odd number of lengths
+    /// is repeated once to make the count even, as in SVG.
+    pub intervals: Vec<f32>,
+    /// How far into the pattern each subpath starts.
+    pub offset: f32,
+}
+
+impl DashPattern {
+    pub fn new(intervals: Vec<f32>) -> Self {
+        Self {
+            intervals,
+            offset: 0.,
+        }
+    }
+
+    pub fn with_offset(self, offset: f32) -> Self {
+        Self { offset, ..self }
+    }
+}
+
+impl Path {
+    /// Creates an empty path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a path from path events, such as those of a `lyon_path::Path`.
+    ///
+    /// The events need not be consistent with each other: each segment is drawn from the
+    /// current point, and `End` events only mark whether the subpath is closed.
+    pub fn from_events(events: impl IntoIterator<Item = PathEvent>) -> Self {
+        let mut path = Path::new();
+        for event in events {
+            match event {
+                PathEvent::Begin { at } => path.move_to(at),
+                PathEvent::Line { to, .. } => path.line_to(to),
+                PathEvent::Quadratic { ctrl, to, .. } => path.quadratic_to(ctrl, to),
+                PathEvent::Cubic {
+                    ctrl1, ctrl2, to, ..
+                } => path.cubic_to(ctrl1, ctrl2, to),
+                PathEvent::End { close, .. } => {
+                    if close {
+                        path.close_path();
+                    }
+                }
+            }
+        }
+        path
+    }
+
+    /// Parses SVG path data, such as the `d` attribute of an SVG `<path>` element.
+    ///
+    /// The M, L, H, V, Q, C, A and Z commands are supported in both their absolute and relative
+    /// forms.
+    pub fn from_svg(data: &str) -> std::result::Result<Self, ParseError> {
+        let mut parser = SvgParser { data, offset: 0 };
+        let mut path = Path::new();
+
+        while let Some((command, offset)) = parser.command()? {
+            if path.events.is_empty() && command != 'M' && command != 'm' {
+                return Err(ParseError::MissingMoveTo);
+            }
+
+            let relative = command.is_ascii_lowercase();
+            let mut command = command.to_ascii_uppercase();
+            loop {
+                let current = path.current_point().unwrap_or_else(P2::origin);
+                let base = if relative {
+                    current.to_vector()
+                } else {
+                    V2::zero()
+                };
+
+                match command {
+                    'M' => {
+                        path.move_to(parser.point()? + base);
+                        // Coordinate pairs following a move are implicit line commands.
+                        command = 'L';
+                    }
+                    'L' => path.line_to(parser.point()? + base),
+                    'H' => path.line_to(P2::new(parser.number()? + base.x, current.y)),
+                    'V' => path.line_to(P2::new(current.x, parser.number()? + base.y)),
+                    'Q' => {
+                        let ctrl = parser.point()? + base;
+                        path.quadratic_to(ctrl, parser.point()? + base);
+                    }
+                    'C' => {
+                        let ctrl0 = parser.point()? + base;
+                        let ctrl1 = parser.point()? + base;
+                        path.cubic_to(ctrl0, ctrl1, parser.point()? + base);
+                    }
+                    'A' => {
+                        let radii = V2::new(parser.number()?, parser.number()?);
+                        let x_rotation = Angle::degrees(parser.number()?);
+                        let large_arc = parser.flag()?;
+                        let sweep = parser.flag()?;
+                        path.arc_to(radii, x_rotation, large_arc, sweep, parser.point()? + base);
+                    }
+                    'Z' => {
+                        path.close_path();
+                        break;
+                    }
+                    _ => return Err(ParseError::UnsupportedCommand(command, offset)),
+                }
+
+                if !parser.has_number() {
+                    break;
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Returns a smooth curve through every point, made of cubic bezier curves following a
+    /// Catmull-Rom spline.
+    ///
+    /// `tension` scales the tangent at each point, which is `tension` times the vector between
+    /// its neighbors; 0.5 gives the standard Catmull-Rom spline and 0 gives straight lines. The
+    /// ends are treated as if the curve continued past them by reflecting their neighbors.
+    pub fn smooth_through(points: &[P2], tension: f32) -> Self {
+        let mut path = Path::new();
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return path,
+        };
+
+        let n = points.len();
+        let point = |i: isize| match i {
+            -1 if n > 1 => first + (first - points[1]),
+            i if i as usize == n && n > 1 => last + (last - points[n - 2]),
+            i => points[i.max(0).min(n as isize - 1) as usize],
+        };
+
+        path.move_to(first);
+        for i in 0..n as isize - 1 {
+            let (before, from, to, after) = (point(i - 1), point(i), point(i + 1), point(i + 2));
+            path.cubic_to(
+                from + (to - before) * tension / 3.,
+                to - (after - from) * tension / 3.,
+                to,
+            );
+        }
+        path
+    }
+
+    /// Returns an iterator over the events of the path.
+    pub fn events<'a>(&'a self) -> impl Iterator<Item = PathEvent> + Clone + 'a {
+        self.events.iter().copied()
+    }
+
+    /// Checks that the path can be drawn, returning an error which points at the index in
+    /// `events()` of the first event with a NaN or infinite coordinate.
+    ///
+    /// Non-finite coordinates usually come from a bad division. Rasterization runs the same
+    /// check, so painting such a path fails the render with this error rather than failing
+    /// inside tessellation or producing garbage geometry.
+    pub fn validate(&self) -> std::result::Result<(), PathError> {
+        validate_events(self.events())
+    }
+
+    /// Returns the point at which the next segment of the path will start, if any.
+    pub fn current_point(&self) -> Option<P2> {
+        match self.events.last() {
+            Some(PathEvent::End { first, close, .. }) if *close => Some(*first),
+            Some(PathEvent::End { last, .. }) => Some(*last),
+            _ => None,
+        }
+    }
+
+    /// Returns the smallest axis aligned rectangle containing the path, or `None` if the path is
+    /// empty.
+    ///
+    /// Curves are bounded by their extrema rather than their control points, so the bounds are
+    /// tight.
+    pub fn bounds(&self) -> Option<Rect<f32>> {
+        if self.events.is_empty() {
+            return None;
+        }
+
+        let starts = self.events().filter_map(|event| match event {
+            PathEvent::Begin { at } => Some(at),
+            _ => None,
+        });
+        let extrema = self.segments().flat_map(|segment| {
+            let bounds = bounding_rect(&segment);
+            ArrayVec::from([bounds.min(), bounds.max()]).into_iter()
+        });
+
+        Some(Rect::from_points(starts.chain(extrema)))
+    }
+
+    /// Returns true if the point is inside the region the path would cover when filled with the
+    /// given rule.
+    ///
+    /// As when filling, open subpaths are treated as closed. Points exactly on the outline are
+    /// inside.
+    pub fn contains(&self, p: P2, fill_rule: FillRule) -> bool {
+        let edges = self.fill_edges();
+        if edges.iter().any(|edge| on_edge(edge, p)) {
+            return true;
+        }
+
+        let winding = winding_number(&edges, p);
+        match fill_rule {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+
+    /// Returns the point on the outline of the path nearest to `p`, and its distance from `p`,
+    /// or `None` if the path is empty.
+    ///
+    /// Only the drawn outline is considered, so open subpaths have no closing edge.
+    pub fn closest_point(&self, p: P2) -> Option<(P2, f32)> {
+        let starts = self.events().filter_map(|event| match event {
+            PathEvent::Begin { at } => Some(at),
+            _ => None,
+        });
+        let nearest = self
+            .segments()
+            .map(|segment| closest_point_on_segment(&segment, p));
+
+        starts
+            .chain(nearest)
+            .map(|q| (q, (q - p).length()))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Returns the length of the drawn outline of the path, including the closing edges of closed
+    /// subpaths. Moves between subpaths add no length.
+    pub fn total_length(&self) -> f32 {
+        self.segments()
+            .map(|segment| length_table(&segment).last().copied().unwrap_or(0.))
+            .sum()
+    }
+
+    /// Returns the point reached after travelling the given distance along the drawn outline of
+    /// the path, or `None` if the path is empty. Unlike the t parameter of a curve, distance
+    /// advances evenly along the outline. It is clamped to `[0, total_length]`.
+    pub fn point_at_length(&self, length: f32) -> Option<P2> {
+        match self.segment_at_length(length) {
+            Some((segment, t)) => Some(segment.sample(t)),
+            None => self.events().find_map(|event| match event {
+                PathEvent::Begin { at } => Some(at),
+                _ => None,
+            }),
+        }
+    }
+
+    /// Returns the unit direction of travel after the given distance along the drawn outline of
+    /// the path, or `None` if the outline has no length. The distance is clamped as in
+    /// `point_at_length`.
+    ///
+    /// At a corner, the tangent is that of the segment arriving at the corner. Segments without
+    /// length have no direction and are skipped.
+    pub fn tangent_at_length(&self, length: f32) -> Option<V2> {
+        let (segment, t) = self.segment_at_length(length)?;
+        let tangent = match derivative(&segment, t) {
+            d if d.square_length() > 0. => d,
+            // Curves with a control point on an endpoint stop there; look just inside instead.
+            _ => derivative(&segment, if t < 0.5 { t + 0.001 } else { t - 0.001 }),
+        };
+        Some(tangent.normalize())
+    }
+
+    /// Returns the unit normal to the left of the direction of travel after the given distance
+    /// along the drawn outline, or `None` if the outline has no length. Left is the tangent
+    /// turned a quarter from +x toward +y, so the normal of a path heading along +x points along
+    /// +y.
+    pub fn normal_at_length(&self, length: f32) -> Option<V2> {
+        self.tangent_at_length(length)
+            .map(|tangent| V2::new(-tangent.y, tangent.x))
+    }
+
+    /// Returns a path of the dashes along this path, as open subpaths following the original
+    /// lines and curves.
+    ///
+    /// The pattern restarts at each subpath and runs continuously across segments, including the
+    /// closing edge of closed subpaths. Patterns with negative or non-finite lengths, or without a
+    /// positive length, leave the path unchanged.
+    pub fn dash(&self, pattern: &DashPattern) -> Self {
+        let mut intervals = pattern.intervals.clone();
+        if intervals.len() % 2 == 1 {
+            intervals.extend_from_slice(&pattern.intervals);
+        }
+        let total = intervals.iter().sum::<f32>();
+        if total <= 0. || !total.is_finite() || intervals.iter().any(|l| *l < 0.) {
+            return self.clone();
+        }
+
+        let mut dashes = Path::new();
+        for (segments, _) in self.subpath_segments() {
+            let mut index = 0;
+            let mut phase = pattern.offset.rem_euclid(total);
+            while phase >= intervals[index] {
+                phase -= intervals[index];
+                index = (index + 1) % intervals.len();
+            }
+            let mut remaining = intervals[index] - phase;
+            let mut in_dash = false;
+
+            for segment in segments {
+                let lengths = length_table(&segment);
+                let length = lengths[lengths.len() - 1];
+                let mut position = 0.;
+                loop {
+                    let step = remaining.min(length - position);
+                    if index % 2 == 0 {
+                        let piece = split_range(
+                            &segment,
+                            t_at_length(&lengths, position),
+                            t_at_length(&lengths, position + step),
+                        );
+                        if !in_dash {
+                            dashes.move_to(piece.from());
+                            in_dash = true;
+                        }
+                        dashes.push_segment(&piece);
+                    }
+
+                    position += step;
+                    remaining -= step;
+                    if remaining > 0. {
+                        break;
+                    }
+
+                    index = (index + 1) % intervals.len();
+                    remaining = intervals[index];
+                    in_dash = false;
+                    if position >= length {
+                        break;
+                    }
+                }
+            }
+        }
+
+        dashes
+    }
+
+    /// Returns the outline of a stroke along the path whose half width varies along its length,
+    /// to be filled with the even-odd rule.
+    ///
+    /// `half_width` maps the distance along each subpath, normalized to `[0, 1]`, to the distance
+    /// from the path to each side of the stroke. It is sampled at each vertex of the flattened
+    /// path. Open subpaths become one closed outline with butt ends, and closed subpaths become
+    /// an outer and an inner ring. Joins are not mitered, so sharp corners are drawn narrower.
+    pub fn variable_stroke(&self, half_width: impl Fn(f32) -> f32) -> Self {
+        let mut outline = Path::new();
+        for (points, closed) in self.stroke_polylines() {
+            let n = points.len();
+            let edge = |i: usize| (points[(i + 1) % n] - points[i]).normalize();
+            let mut distances = vec![0.];
+            for i in 1..n {
+                distances.push(distances[i - 1] + (points[i] - points[i - 1]).length());
+            }
+            let length = distances[n - 1]
+                + if closed {
+                    (points[0] - points[n - 1]).length()
+                } else {
+                    0.
+                };
+
+            let (left, right): (Vec<P2>, Vec<P2>) = (0..n)
+                .map(|i| {
+                    let direction = match (closed, i) {
+                        (false, 0) => edge(0),
+                        (false, i) if i == n - 1 => edge(n - 2),
+                        _ => {
+                            let (before, after) = (edge((i + n - 1) % n), edge(i));
+                            // Where the path doubles back the averaged direction vanishes, so
+                            // the side is offset along the incoming edge's normal instead.
+                            if (before + after).square_length() < 1e-6 {
+                                before
+                            } else {
+                                before + after
+                            }
+                        }
+                    };
+                    let normal = V2::new(-direction.y, direction.x).normalize();
+                    let offset = normal * half_width(distances[i] / length);
+                    (points[i] + offset, points[i] - offset)
+                })
+                .unzip();
+
+            if closed {
+                outline.polyline(&left, /*close=*/ true);
+                outline.polyline(&right, /*close=*/ true);
+            } else {
+                let ring = left
+                    .into_iter()
+                    .chain(right.into_iter().rev())
+                    .collect::<Vec<_>>();
+                outline.polyline(&ring, /*close=*/ true);
+            }
+        }
+
+        outline
+    }
+
+    /// Returns the outline of a stroke of the given width along the path, to be filled with the
+    /// non-zero rule.
+    ///
+    /// The outline is built from pieces which each cover part of the stroke: one for every
+    /// flattened edge, join and cap. All pieces wind the same way, so where they overlap the
+    /// non-zero rule still fills them once. Miter joins longer than `miter_limit` widths are
+    /// drawn as bevels, as in `Canvas::set_miter_limit`; limits below 1 are raised to 1.
+    pub fn outline_stroke(
+        &self,
+        width: f32,
+        cap: LineCap,
+        join: LineJoin,
+        miter_limit: f32,
+    ) -> Self {
+        let half_width = width / 2.;
+        let miter_limit = miter_limit.max(1.);
+        let mut pieces = vec![];
+        for (points, closed) in self.stroke_polylines() {
+            let n = points.len();
+            let edges = if closed { n } else { n - 1 };
+            let direction = |i: usize| (points[(i + 1) % n] - points[i]).normalize();
+            let normal = |i: usize| {
+                let d = direction(i);
+                V2::new(-d.y, d.x) * half_width
+            };
+
+            for i in 0..edges {
+                let (a, b, offset) = (points[i], points[(i + 1) % n], normal(i));
+                pieces.push(vec![a + offset, b + offset, b - offset, a - offset]);
+            }
+
+            let joins = if closed { 0..n } else { 1..n - 1 };
+            for i in joins {
+                let (before, after) = ((i + edges - 1) % edges, i % edges);
+                pieces.extend(stroke_join(
+                    points[i],
+                    normal(before),
+                    normal(after),
+                    direction(before).cross(direction(after)),
+                    join,
+                    miter_limit,
+                ));
+            }
+
+            if !closed {
+                let (start, end) = (direction(0), direction(n - 2));
+                pieces.extend(stroke_cap(points[0], -start * half_width, cap));
+                pieces.extend(stroke_cap(points[n - 1], end * half_width, cap));
+            }
+        }
+
+        let mut outline = Path::new();
+        for mut piece in pieces {
+            let area = signed_area(&piece);
+            if area == 0. {
+                continue;
+            } else if area < 0. {
+                piece.reverse();
+            }
+            outline.polyline(&piece, /*close=*/ true);
+        }
+        outline
+    }
+
+    /// Returns how many times the path winds around the point, as filled paths see it, so
+    /// that open subpaths are treated as closed.
+    ///
+    /// Each subpath adds one for every turn it makes around the point in the direction which
+    /// rotates +x toward +y, and subtracts one for every turn the opposite way, measured in the
+    /// path's own coordinates. Points exactly on the outline get the winding of one side.
+    pub fn winding_at(&self, p: P2) -> i32 {
+        winding_number(&self.fill_edges(), p)
+    }
+
+    /// Returns the path with every point mapped through the given transform.
+    ///
+    /// Bezier curves are invariant under affine transforms, so transforming their control points
+    /// transforms the curves exactly.
+    pub fn transform(&self, transform: &Transform2D<f32>) -> Self {
+        Self {
+            events: self
+                .events()
+                .map(|event| map_points(event, |p| transform.transform_point(p)))
+                .collect(),
+        }
+    }
+
+    /// Returns the path traced in the opposite direction. The subpaths come in reverse order and
+    /// each runs from its end back to its start, with curve control points swapped to match.
+    /// Closed subpaths stay closed.
+    pub fn reverse(&self) -> Self {
+        let mut subpaths = vec![];
+        let mut segments = vec![];
+        for event in self.events() {
+            match event {
+                PathEvent::Begin { .. } => segments.clear(),
+                PathEvent::End { last, first, close } => {
+                    let mut subpath = vec![PathEvent::Begin { at: last }];
+                    subpath.extend(segments.drain(..).rev().map(reverse_segment));
+                    subpath.push(PathEvent::End {
+                        last: first,
+                        first: last,
+                        close,
+                    });
+                    subpaths.push(subpath);
+                }
+                event => segments.push(event),
+            }
+        }
+        Self {
+            events: subpaths.into_iter().rev().flatten().collect(),
+        }
+    }
+
+    /// Starts a new subpath at the given point.
+    pub fn move_to(&mut self, dest: P2) {
+        self.events.push(PathEvent::Begin { at: dest });
+        self.events.push(PathEvent::End {
+            last: dest,
+            first: dest,
+            close: false,
+        });
+    }
+
+    /// Adds a line to the path which ends at the given point.
+    pub fn line_to(&mut self, dest: P2) {
+        self.extend_subpath(dest, |from| PathEvent::Line { from, to: dest });
+    }
+
+    /// Adds a quadratic bezier curve to the path with the given control and end points.
+    pub fn quadratic_to(&mut self, ctrl: P2, dest: P2) {
+        self.extend_subpath(dest, |from| PathEvent::Quadratic {
+            from,
+            ctrl,
+            to: dest,
+        });
+    }
+
+    /// Adds a cubic bezier curve to the path with the given control and end points.
+    pub fn cubic_to(&mut self, ctrl0: P2, ctrl1: P2, dest: P2) {
+        self.extend_subpath(dest, |from| PathEvent::Cubic {
+            from,
+            ctrl1: ctrl0,
+            ctrl2: ctrl1,
+            to: dest,
+        });
+    }
+
+    /// Adds an elliptical arc to the path which ends at the given point, following the SVG arc
+    /// notation. The arc is approximated by cubic bezier curves which each span at most a quarter
+    /// turn.
+    pub fn arc_to(&mut self, radii: V2, x_rotation: Angle, large_arc: bool, sweep: bool, dest: P2) {
+        let from = match self.current_point() {
+            Some(from) => from,
+            None => return self.move_to(dest),
+        };
+
+        let arc = SvgArc {
+            from,
+            to: dest,
+            radii: V2::new(radii.x.abs(), radii.y.abs()),
+            x_rotation,
+            flags: ArcFlags { large_arc, sweep },
+        };
+        if arc.is_straight_line() {
+            return self.line_to(dest);
+        }
+
+        let mut curves = vec![];
+        arc.for_each_cubic_bezier(&mut |curve| curves.push(*curve));
+        if let Some(last) = curves.last_mut() {
+            last.to = dest;
+        }
+        curves
+            .into_iter()
+            .for_each(|curve| self.cubic_to(curve.ctrl1, curve.ctrl2, curve.to));
+    }
+
+    /// Closes the current subpath.
+    pub fn close_path(&mut self) {
+        if let Some(PathEvent::End { close, .. }) = self.events.last_mut() {
+            *close = true;
+        }
+    }
+
+    /// Adds the subpaths of another path after those of this path. The other path's subpaths
+    /// keep their own starting points, so they are never joined to this path's last subpath.
+    pub fn append(&mut self, other: &Path) {
+        self.events.extend(other.events());
+    }
+
+    /// Returns an iterator over the segments of the path, including the closing edges of closed
+    /// subpaths.
+    fn segments<'a>(&'a self) -> impl Iterator<Item = BezierSegment<f32>> + 'a {
+        self.events().filter_map(|event| match event {
+            PathEvent::End { last, first, close } if close && last != first => {
+                Some(BezierSegment::Linear(LineSegment {
+                    from: last,
+                    to: first,
+                }))
+            }
+            event => bezier_segment(event),
+        })
+    }
+
+    /// Returns the drawn segments of each subpath, including the closing edges of closed
+    /// subpaths, and whether each subpath is closed.
+    fn subpath_segments(&self) -> Vec<(Vec<BezierSegment<f32>>, bool)> {
+        let mut subpaths = vec![];
+        let mut segments = vec![];
+        for event in self.events() {
+            match event {
+                PathEvent::End { last, first, close } => {
+                    if close && last != first {
+                        segments.push(BezierSegment::Linear(LineSegment {
+                            from: last,
+                            to: first,
+                        }));
+                    }
+                    subpaths.push((std::mem::take(&mut segments), close));
+                }
+                event => segments.extend(bezier_segment(event)),
+            }
+        }
+        subpaths
+    }
+
+    /// Returns the segment of the drawn outline reached after travelling the given distance, and
+    /// the t along it at which the distance is reached. Distances between segments resolve to the
+    /// end of the earlier one, and segments without length are skipped.
+    fn segment_at_length(&self, length: f32) -> Option<(BezierSegment<f32>, f32)> {
+        let mut remaining = length.max(0.);
+        let mut end = None;
+        for segment in self.segments() {
+            let lengths = length_table(&segment);
+            let segment_length = lengths[lengths.len() - 1];
+            if segment_length <= 0. {
+                continue;
+            }
+            if remaining <= segment_length {
+                return Some((segment, t_at_length(&lengths, remaining)));
+            }
+            remaining -= segment_length;
+            end = Some((segment, 1.));
+        }
+        end
+    }
+
+    /// Returns the flattened vertices of each subpath to stroke, without repeated points, and
+    /// whether each subpath is closed. Subpaths without length are left out.
+    fn stroke_polylines(&self) -> Vec<(Vec<P2>, bool)> {
+        let mut polylines = vec![];
+        for (segments, closed) in self.subpath_segments() {
+            let mut points = segments.first().map(|s| vec![s.from()]).unwrap_or_default();
+            for segment in &segments {
+                for_each_flattened(segment, FLATTEN_TOLERANCE, &mut |edge| {
+                    if points.last() != Some(&edge.to) {
+                        points.push(edge.to);
+                    }
+                });
+            }
+            if closed && points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            if points.len() >= 2 {
+                polylines.push((points, closed));
+            }
+        }
+        polylines
+    }
+
+    /// Adds a subpath through the given points.
+    fn polyline(&mut self, points: &[P2], close: bool) {
+        self.move_to(points[0]);
+        points[1..].iter().for_each(|p| self.line_to(*p));
+        if close {
+            self.close_path();
+        }
+    }
+
+    /// Appends a segment to the current subpath, starting from the current point.
+    fn push_segment(&mut self, segment: &BezierSegment<f32>) {
+        match segment {
+            BezierSegment::Linear(line) => self.line_to(line.to),
+            BezierSegment::Quadratic(curve) => self.quadratic_to(curve.ctrl, curve.to),
+            BezierSegment::Cubic(curve) => self.cubic_to(curve.ctrl1, curve.ctrl2, curve.to),
+        }
+    }
+
+    /// Returns the edges of the region the path covers when filled: every segment with curves
+    /// flattened, plus an edge closing each subpath.
+    fn fill_edges(&self) -> Vec<LineSegment<f32>> {
+        let mut edges = vec![];
+        for event in self.events() {
+            let segment = match event {
+                PathEvent::End { last, first, .. } if last != first => {
+                    Some(BezierSegment::Linear(LineSegment {
+                        from: last,
+                        to: first,
+                    }))
+                }
+                event => bezier_segment(event),
+            };
+            if let Some(segment) = segment {
+                for_each_flattened(&segment, FLATTEN_TOLERANCE, &mut |edge| edges.push(edge));
+            }
+        }
+        edges
+    }
+
+    /// Returns the vertices of each subpath with curves flattened into line segments which stray
+    /// at most `tolerance` from the curve. The closing vertex of a closed subpath is not repeated.
+    pub(crate) fn flattened_subpaths(&self, tolerance: f32) -> Vec<Vec<P2>> {
+        let mut subpaths = vec![];
+        let mut vertices = vec![];
+        for event in self.events() {
+            match event {
+                PathEvent::Begin { at } => vertices.push(at),
+                PathEvent::End { first, .. } => {
+                    if vertices.len() > 1 && vertices.last() == Some(&first) {
+                        vertices.pop();
+                    }
+                    subpaths.push(std::mem::take(&mut vertices));
+                }
+                event => {
+                    if let Some(segment) = bezier_segment(event) {
+                        for_each_flattened(&segment, tolerance, &mut |edge| vertices.push(edge.to));
+                    }
+                }
+            }
+        }
+        subpaths
+    }
+
+    /// Appends a segment built from the current point to the current subpath. If there is no
+    /// current point, a subpath is started at `dest` instead.
+    fn extend_subpath(&mut self, dest: P2, segment: impl FnOnce(P2) -> PathEvent) {
+        let (first, from) = match self.events.last().copied() {
+            Some(PathEvent::End { first, close, .. }) if close => {
+                self.events.push(PathEvent::Begin { at: first });
+                (first, first)
+            }
+            Some(PathEvent::End { first, last, .. }) => {
+                self.events.pop();
+                (first, last)
+            }
+            _ => return self.move_to(dest),
+        };
+
+        self.events.push(segment(from));
+        self.events.push(PathEvent::End {
+            last: dest,
+            first,
+            close: false,
+        });
+    }
+}
+
+impl Paint for Path {
+    fn paint(&self, canvas: &mut Canvas) {
+        canvas.paint(self.events())
+    }
+}
+
+impl Paint for &Path {
+    fn paint(&self, canvas: &mut Canvas) {
+        (**self).paint(canvas);
+    }
+}
+
+/// Scales the path about the center of its bounds.
+impl Scale for Path {
+    fn scale(self, factor: f32) -> Self {
+        let center = match self.bounds() {
+            Some(bounds) => bounds.center().to_vector(),
+            None => return self,
+        };
+        self.transform(
+            &Transform2D::create_translation(-center.x, -center.y)
+                .post_scale(factor, factor)
+                .post_translate(center),
+        )
+    }
+}
+
+impl Translate for Path {
+    fn translate(self, translation: V2) -> Self {
+        self.transform(&Transform2D::create_translation(
+            translation.x,
+            translation.y,
+        ))
+    }
+}
+
+impl Rotate for Path {
+    fn rotate(self, pivot: P2, theta: Angle) -> Self {
+        // Euclid's rotations turn from +x toward -y; negate to match `P2::rotate`.
+        self.transform(
+            &Transform2D::create_translation(-pivot.x, -pivot.y)
+                .post_rotate(-theta)
+                .post_translate(pivot.to_vector()),
+        )
+    }
+}
+
+fn map_points(event: PathEvent, mut f: impl FnMut(P2) -> P2) -> PathEvent {
+    match event {
+        PathEvent::Begin { at } => PathEvent::Begin { at: f(at) },
+        PathEvent::Line { from, to } => PathEvent::Line {
+            from: f(from),
+            to: f(to),
+        },
+        PathEvent::Quadratic { from, ctrl, to } => PathEvent::Quadratic {
+            from: f(from),
+            ctrl: f(ctrl),
+            to: f(to),
+        },
+        PathEvent::Cubic {
+            from,
+            ctrl1,
+            ctrl2,
+            to,
+        } => PathEvent::Cubic {
+            from: f(from),
+            ctrl1: f(ctrl1),
+            ctrl2: f(ctrl2),
+            to: f(to),
+        },
+        PathEvent::End { last, first, close } => PathEvent::End {
+            last: f(last),
+            first: f(first),
+            close,
+        },
+    }
+}
+
+/// Returns an error pointing at the first event with a NaN or infinite coordinate.
+pub(crate) fn validate_events(
+    mut events: impl Iterator<Item = PathEvent>,
+) -> std::result::Result<(), PathError> {
+    let invalid = events.position(|event| {
+        let mut finite = true;
+        map_points(event, |p| {
+            finite &= p.x.is_finite() && p.y.is_finite();
+            p
+        });
+        !finite
+    });
+    match invalid {
+        Some(i) => Err(PathError::NonFiniteCoordinate(i)),
+        None => Ok(()),
+    }
+}
+
+/// Returns the piece of a stroke outline filling the outside of the corner at `vertex`, where
+/// the edges have the given normals of half the stroke width and `turn` is the cross product of
+/// their directions. Miters longer than `miter_limit` half widths are drawn as bevels.
+fn stroke_join(
+    vertex: P2,
+    before: V2,
+    after: V2,
+    turn: f32,
+    join: LineJoin,
+    miter_limit: f32,
+) -> Option<Vec<P2>> {
+    if turn == 0. {
+        // Where the path doubles back, only a round join reaches past the tip; miters and
+        // bevels of a reversal have no area.
+        return match join {
+            LineJoin::Round if before.dot(after) < 0. => {
+                let mut fan = vec![vertex];
+                fan.extend(arc_points(vertex, before, -std::f32::consts::PI));
+                Some(fan)
+            }
+            _ => None,
+        };
+    }
+
+    // The outside of the corner is to the right of the path when it turns toward its normals.
+    let (before, after) = if turn > 0. {
+        (-before, -after)
+    } else {
+        (before, after)
+    };
+    let bevel = vec![vertex, vertex + before, vertex + after];
+    match join {
+        LineJoin::Bevel => Some(bevel),
+        LineJoin::Miter | LineJoin::MiterClip => {
+            // The miter reaches 2 / |n0 + n1| half widths past the vertex, for unit normals.
+            let sum = before + after;
+            let ratio = 2. * before.length() / sum.length();
+            if ratio > miter_limit {
+                return Some(bevel);
+            }
+            let tip = vertex + sum.normalize() * before.length() * ratio;
+            Some(vec![vertex, vertex + before, tip, vertex + after])
+        }
+        LineJoin::Round => {
+            let sweep = before.cross(after).atan2(before.dot(after));
+            let mut fan = vec![vertex];
+            fan.extend(arc_points(vertex, before, sweep));
+            Some(fan)
+        }
+    }
+}
+
+/// Returns the piece of a stroke outline covering the cap at the end of a subpath, where
+/// `outward` points away from the subpath with a length of half the stroke width.
+fn stroke_cap(end: P2, outward: V2, cap: LineCap) -> Option<Vec<P2>> {
+    let side = V2::new(-outward.y, outward.x);
+    match cap {
+        LineCap::Butt => None,
+        LineCap::Square => Some(vec![
+            end + side,
+            end + side + outward,
+            end - side + outward,
+            end - side,
+        ]),
+        LineCap::Round => Some(arc_points(end, side, -std::f32::consts::PI)),
+    }
+}
+
+/// Returns points along the arc around `center` which starts at `center + start` and turns by
+/// `sweep` radians, spaced so the chords between them stray at most `FLATTEN_TOLERANCE` from the
+/// arc.
+fn arc_points(center: P2, start: V2, sweep: f32) -> Vec<P2> {
+    let radius = start.length();
+    let max_step = if radius > FLATTEN_TOLERANCE {
+        2. * (1. - FLATTEN_TOLERANCE / radius).acos()
+    } else {
+        std::f32::consts::FRAC_PI_2
+    };
+    let steps = (sweep.abs() / max_step).ceil().max(1.) as usize;
+    (0..=steps)
+        .map(|i| {
+            let (sin, cos) = (sweep * i as f32 / steps as f32).sin_cos();
+            center + V2::new(start.x * cos - start.y * sin, start.x * sin + start.y * cos)
+        })
+        .collect()
+}
+
+/// Returns the area of the polygon with the given vertices, positive when it winds from +x
+/// toward +y.
+pub(crate) fn signed_area(vertices: &[P2]) -> f32 {
+    (0..vertices.len())
+        .map(|i| {
+            let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+            a.to_vector().cross(b.to_vector())
+        })
+        .sum::<f32>()
+        / 2.
+}
+
+/// Returns the segment event traced from its end to its start.
+fn reverse_segment(event: PathEvent) -> PathEvent {
+    match event {
+        PathEvent::Line { from, to } => PathEvent::Line { from: to, to: from },
+        PathEvent::Quadratic { from, ctrl, to } => PathEvent::Quadratic {
+            from: to,
+            ctrl,
+            to: from,
+        },
+        PathEvent::Cubic {
+            from,
+            ctrl1,
+            ctrl2,
+            to,
+        } => PathEvent::Cubic {
+            from: to,
+            ctrl1: ctrl2,
+            ctrl2: ctrl1,
+            to: from,
+        },
+        event => event,
+    }
+}
+
+fn bezier_segment(event: PathEvent) -> Option<BezierSegment<f32>> {
+    match event {
+        PathEvent::Line { from, to } => Some(BezierSegment::Linear(LineSegment { from, to })),
+        PathEvent::Quadratic { from, ctrl, to } => {
+            Some(BezierSegment::Quadratic(QuadraticBezierSegment {
+                from,
+                ctrl,
+                to,
+            }))
+        }
+        PathEvent::Cubic {
+            from,
+            ctrl1,
+            ctrl2,
+            to,
+        } => Some(BezierSegment::Cubic(CubicBezierSegment {
+            from,
+            ctrl1,
+            ctrl2,
+            to,
+        })),
+        _ => None,
+    }
+}
+
+fn derivative(segment: &BezierSegment<f32>, t: f32) -> V2 {
+    match segment {
+        BezierSegment::Linear(line) => line.to_vector(),
+        BezierSegment::Quadratic(curve) => curve.derivative(t),
+        BezierSegment::Cubic(curve) => curve.derivative(t),
+    }
+}
+
+fn split_range(segment: &BezierSegment<f32>, t0: f32, t1: f32) -> BezierSegment<f32> {
+    match segment {
+        BezierSegment::Linear(line) => BezierSegment::Linear(line.split_range(t0..t1)),
+        BezierSegment::Quadratic(curve) => BezierSegment::Quadratic(curve.split_range(t0..t1)),
+        BezierSegment::Cubic(curve) => BezierSegment::Cubic(curve.split_range(t0..t1)),
+    }
+}
+
+/// Returns the arc length of the segment from its start to evenly spaced values of t, ending
+/// with the length of the whole segment.
+fn length_table(segment: &BezierSegment<f32>) -> Vec<f32> {
+    const SAMPLES: usize = 64;
+
+    if let BezierSegment::Linear(line) = segment {
+        return vec![0., line.length()];
+    }
+
+    let mut length = 0.;
+    let mut last = segment.from();
+    std::iter::once(0.)
+        .chain((1..=SAMPLES).map(|i| {
+            let p = segment.sample(i as f32 / SAMPLES as f32);
+            length += (p - last).length();
+            last = p;
+            length
+        }))
+        .collect()
+}
+
+/// Returns the value of t at which the segment described by the length table reaches the given
+/// arc length.
+fn t_at_length(lengths: &[f32], length: f32) -> f32 {
+    let steps = (lengths.len() - 1) as f32;
+    match lengths.iter().position(|l| *l >= length) {
+        Some(0) => 0.,
+        Some(i) => {
+            let (before, after) = (lengths[i - 1], lengths[i]);
+            let fraction = if after > before {
+                (length - before) / (after - before)
+            } else {
+                0.
+            };
+            (i as f32 - 1. + fraction) / steps
+        }
+        None => 1.,
+    }
+}
+
+fn for_each_flattened(
+    segment: &BezierSegment<f32>,
+    tolerance: f32,
+    f: &mut impl FnMut(LineSegment<f32>),
+) {
+    let mut from = segment.from();
+    let mut push = |to| {
+        f(LineSegment { from, to });
+        from = to;
+    };
+    match segment {
+        BezierSegment::Linear(line) => push(line.to),
+        BezierSegment::Quadratic(curve) => curve.for_each_flattened(tolerance, &mut push),
+        BezierSegment::Cubic(curve) => curve.for_each_flattened(tolerance, &mut push),
+    }
+}
+
+fn closest_point_on_segment(segment: &BezierSegment<f32>, p: P2) -> P2 {
+    match segment {
+        BezierSegment::Linear(line) => {
+            let direction = line.to - line.from;
+            let length_squared = direction.square_length();
+            if length_squared == 0. {
+                return line.from;
+            }
+            let t = (p - line.from).dot(direction) / length_squared;
+            line.sample(t.max(0.).min(1.))
+        }
+        BezierSegment::Quadratic(curve) => {
+            let second_derivative =
+                (curve.from.to_vector() - curve.ctrl.to_vector() * 2. + curve.to.to_vector()) * 2.;
+            closest_point_on_curve(
+                p,
+                |t| curve.sample(t),
+                |t| curve.derivative(t),
+                |_| second_derivative,
+            )
+        }
+        BezierSegment::Cubic(curve) => {
+            let [p0, p1, p2, p3] = [curve.from, curve.ctrl1, curve.ctrl2, curve.to];
+            let start = p0.to_vector() - p1.to_vector() * 2. + p2.to_vector();
+            let end = p1.to_vector() - p2.to_vector() * 2. + p3.to_vector();
+            closest_point_on_curve(
+                p,
+                |t| curve.sample(t),
+                |t| curve.derivative(t),
+                |t| (start * (1. - t) + end * t) * 6.,
+            )
+        }
+    }
+}
+
+/// Finds the point on a curve nearest to `p` by sampling the curve coarsely, then refining the
+/// nearest sample with Newton's method on the derivative of the squared distance.
+fn closest_point_on_curve(
+    p: P2,
+    sample: impl Fn(f32) -> P2,
+    derivative: impl Fn(f32) -> V2,
+    second_derivative: impl Fn(f32) -> V2,
+) -> P2 {
+    const SAMPLES: usize = 16;
+    const ITERATIONS: usize = 8;
+
+    let distance = |t: f32| (sample(t) - p).square_length();
+    let seed = (0..=SAMPLES)
+        .map(|i| i as f32 / SAMPLES as f32)
+        .min_by(|a, b| {
+            distance(*a)
+                .partial_cmp(&distance(*b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(0.);
+
+    let mut t = seed;
+    for _ in 0..ITERATIONS {
+        let offset = sample(t) - p;
+        let d = derivative(t);
+        let slope = offset.dot(d);
+        let curvature = d.dot(d) + offset.dot(second_derivative(t));
+        if curvature <= 0. {
+            break;
+        }
+        let next = (t - slope / curvature).max(0.).min(1.);
+        if (next - t).abs() < 1e-6 {
+            t = next;
+            break;
+        }
+        t = next;
+    }
+
+    if distance(t) < distance(seed) {
+        sample(t)
+    } else {
+        sample(seed)
+    }
+}
+
+fn on_edge(edge: &LineSegment<f32>, p: P2) -> bool {
+    let (from, to) = (edge.from, edge.to);
+    (to - from).cross(p - from) == 0.
+        && p.x >= from.x.min(to.x)
+        && p.x <= from.x.max(to.x)
+        && p.y >= from.y.min(to.y)
+        && p.y <= from.y.max(to.y)
+}
+
+/// Counts how many times the edges wind around the point, by the signed crossings of a ray cast
+/// from the point in the +x direction.
+fn winding_number(edges: &[LineSegment<f32>], p: P2) -> i32 {
+    edges
+        .iter()
+        .map(|edge| {
+            let side = (edge.to - edge.from).cross(p - edge.from);
+            if edge.from.y <= p.y && edge.to.y > p.y && side > 0. {
+                1
+            } else if edge.from.y > p.y && edge.to.y <= p.y && side < 0. {
+                -1
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+fn bounding_rect(segment: &BezierSegment<f32>) -> Rect<f32> {
+    match segment {
+        BezierSegment::Linear(line) => line.bounding_rect(),
+        BezierSegment::Quadratic(curve) => curve.bounding_rect(),
+        BezierSegment::Cubic(curve) => curve.bounding_rect(),
+    }
+}
+
+struct SvgParser<'a> {
+    data: &'a str,
+    offset: usize,
+}
+
+impl<'a> SvgParser<'a> {
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.data[self.offset..];
+        let separators = rest
+            .find(|c: char| !c.is_whitespace() && c != ',')
+            .unwrap_or(rest.len());
+        self.offset += separators;
+        self.data[self.offset..].chars().next()
+    }
+
+    fn command(&mut self) -> std::result::Result<Option<(char, usize)>, ParseError> {
+        match self.peek() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                let offset = self.offset;
+                self.offset += 1;
+                Ok(Some((c, offset)))
+            }
+            Some(c) => Err(ParseError::UnexpectedCharacter(c, self.offset)),
+            None => Ok(None),
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        match self.peek() {
+            Some(c) => c.is_ascii_digit() || c == '-' || c == '+' || c == '.',
+            None => false,
+        }
+    }
+
+    fn number(&mut self) -> std::result::Result<f32, ParseError> {
+        self.peek();
+        let start = self.offset;
+        let bytes = self.data.as_bytes();
+        let digits = |mut i: usize| {
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            i
+        };
+
+        let mut end = start;
+        if end < bytes.len() && (bytes[end] == b'-' || bytes[end] == b'+') {
+            end += 1;
+        }
+        let integer_end = digits(end);
+        let mut has_digits = integer_end > end;
+        end = integer_end;
+        if end < bytes.len() && bytes[end] == b'.' {
+            let fraction_end = digits(end + 1);
+            has_digits |= fraction_end > end + 1;
+            end = fraction_end;
+        }
+        if !has_digits {
+            return Err(ParseError::ExpectedNumber(start));
+        }
+        if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+            let mut exponent = end + 1;
+            if exponent < bytes.len() && (bytes[exponent] == b'-' || bytes[exponent] == b'+') {
+                exponent += 1;
+            }
+            let exponent_end = digits(exponent);
+            if exponent_end > exponent {
+                end = exponent_end;
+            }
+        }
+
+        self.offset = end;
+        self.data[start..end]
+            .parse()
+            .map_err(|_| ParseError::ExpectedNumber(start))
+    }
+
+    fn flag(&mut self) -> std::result::Result<bool, ParseError> {
+        let flag = match self.peek() {
+            Some('0') => false,
+            Some('1') => true,
+            _ => return Err(ParseError::ExpectedFlag(self.offset)),
+        };
+        self.offset += 1;
+        Ok(flag)
+    }
+
+    fn point(&mut self) -> std::result::Result<P2, ParseError> {
+        let x = self.number()?;
+        Ok(P2::new(x, self.number()?))
+    }
+}
 
 /// An adapter for iterators over points that implements `Path`.
 #[derive(Debug, Copy, Clone)]
@@ -58,3 +1385,562 @@ where
         result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lyon_tessellation::StrokeOptions;
+
+    #[test]
+    fn svg_lines() {
+        let mut expected = Path::new();
+        expected.move_to(P2::new(0., 0.));
+        expected.line_to(P2::new(2., 0.));
+        expected.line_to(P2::new(2., 2.));
+        expected.close_path();
+
+        assert_eq!(Path::from_svg("M0 0 L2 0 L2 2 Z"), Ok(expected.clone()));
+        assert_eq!(Path::from_svg("m0,0 2,0 v2z"), Ok(expected.clone()));
+        assert_eq!(Path::from_svg("M0 0H2V2z"), Ok(expected));
+    }
+
+    #[test]
+    fn svg_curves() {
+        let mut expected = Path::new();
+        expected.move_to(P2::new(0., 0.));
+        expected.cubic_to(P2::new(1., 1.), P2::new(2., 1.), P2::new(3., 0.));
+        expected.quadratic_to(P2::new(4., -1.), P2::new(5., 0.));
+
+        assert_eq!(
+            Path::from_svg("M0 0 C1 1 2 1 3 0 Q4-1 5 0"),
+            Ok(expected.clone())
+        );
+        assert_eq!(Path::from_svg("M0 0 c1 1 2 1 3 0 q1-1 2 0"), Ok(expected));
+    }
+
+    #[test]
+    fn svg_subpaths() {
+        let mut expected = Path::new();
+        expected.move_to(P2::new(1., 1.));
+        expected.line_to(P2::new(2., 1.));
+        expected.close_path();
+        expected.line_to(P2::new(1., 3.));
+
+        assert_eq!(Path::from_svg("M1 1 L2 1 Z l0 2"), Ok(expected));
+    }
+
+    #[test]
+    fn quarter_circle_arc() {
+        let path = Path::from_svg("M1 0 A1 1 0 0 1 0 1").expect("to parse arc");
+        assert_eq!(
+            path.events().next(),
+            Some(PathEvent::Begin {
+                at: P2::new(1., 0.)
+            })
+        );
+        assert_eq!(path.current_point(), Some(P2::new(0., 1.)));
+
+        let curves = path
+            .events()
+            .filter_map(|event| match event {
+                PathEvent::Begin { .. } | PathEvent::End { .. } => None,
+                PathEvent::Cubic {
+                    from,
+                    ctrl1,
+                    ctrl2,
+                    to,
+                } => Some(CubicBezierSegment {
+                    from,
+                    ctrl1,
+                    ctrl2,
+                    to,
+                }),
+                event => panic!("Expected a cubic curve; got {:?}", event),
+            })
+            .collect::<Vec<_>>();
+        assert!(!curves.is_empty());
+        for curve in curves {
+            for i in 0..=8 {
+                let radius = curve.sample(i as f32 / 8.).to_vector().length();
+                assert!((radius - 1.).abs() < 0.005, "radius {} is not 1", radius);
+            }
+        }
+
+        assert_eq!(
+            Path::from_svg("M1 0 a1 1 0 011-1").map(|p| p.current_point()),
+            Ok(Some(P2::new(2., -1.)))
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        assert_eq!(Path::new().bounds(), None);
+
+        let triangle = Path::from_svg("M1 1 L4 2 L2 5 Z").expect("to parse triangle");
+        assert_eq!(
+            triangle.bounds(),
+            Some(Rect::from_points([P2::new(1., 1.), P2::new(4., 5.)].iter()))
+        );
+
+        let mut bulge = Path::new();
+        bulge.move_to(P2::new(0., 0.));
+        bulge.quadratic_to(P2::new(1., 2.), P2::new(2., 0.));
+        assert_eq!(
+            bulge.bounds(),
+            Some(Rect::from_points([P2::new(0., 0.), P2::new(2., 1.)].iter()))
+        );
+    }
+
+    fn assert_near(a: P2, b: P2) {
+        assert!((a - b).length() < 0.0001, "{:?} is not near {:?}", a, b);
+    }
+
+    #[test]
+    fn rotate() {
+        let triangle = Path::from_svg("M1 0 L2 0 L2 1 Z").expect("to parse triangle");
+        let rotated = triangle.rotate(P2::origin(), Angle::degrees(90.));
+
+        let vertices = rotated
+            .events()
+            .filter_map(|event| match event {
+                PathEvent::Begin { at } => Some(at),
+                PathEvent::Line { to, .. } => Some(to),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vertices.len(), 3);
+        assert_near(vertices[0], P2::new(0., 1.));
+        assert_near(vertices[1], P2::new(0., 2.));
+        assert_near(vertices[2], P2::new(-1., 2.));
+    }
+
+    #[test]
+    fn scale_and_translate() {
+        let curve = Path::from_svg("M0 0 C1 3 3 3 4 0").expect("to parse curve");
+        let bounds = curve.bounds().expect("curve bounds");
+
+        let scaled = curve.clone().scale(2.).bounds().expect("scaled bounds");
+        assert_near(scaled.center(), bounds.center());
+        assert!((scaled.size.width - bounds.size.width * 2.).abs() < 0.0001);
+        assert!((scaled.size.height - bounds.size.height * 2.).abs() < 0.0001);
+
+        let translated = curve.translate(V2::new(1., -1.)).bounds();
+        assert_eq!(translated, Some(bounds.translate(V2::new(1., -1.))));
+    }
+
+    #[test]
+    fn contains() {
+        let quadrilateral =
+            Path::from_svg("M0 0 L4 0 L5 4 L1 4 Z").expect("to parse quadrilateral");
+        for rule in &[FillRule::EvenOdd, FillRule::NonZero] {
+            assert!(quadrilateral.contains(P2::new(2., 2.), *rule));
+            assert!(quadrilateral.contains(P2::new(0., 0.), *rule));
+            assert!(quadrilateral.contains(P2::new(5., 4.), *rule));
+            assert!(quadrilateral.contains(P2::new(2., 0.), *rule));
+            assert!(!quadrilateral.contains(P2::new(0., 2.), *rule));
+            assert!(!quadrilateral.contains(P2::new(6., 2.), *rule));
+            assert!(!quadrilateral.contains(P2::new(-1., 0.), *rule));
+        }
+
+        let nested = Path::from_svg("M0 0 H4 V4 H0 Z M1 1 H3 V3 H1 Z").expect("to parse squares");
+        assert!(nested.contains(P2::new(2., 2.), FillRule::NonZero));
+        assert!(!nested.contains(P2::new(2., 2.), FillRule::EvenOdd));
+        assert!(nested.contains(P2::new(0.5, 2.), FillRule::EvenOdd));
+
+        let open_triangle = Path::from_svg("M0 0 L4 0 L0 4").expect("to parse triangle");
+        assert!(open_triangle.contains(P2::new(1., 1.), FillRule::NonZero));
+
+        let circle =
+            Path::from_svg("M1 0 A1 1 0 0 1 -1 0 A1 1 0 0 1 1 0 Z").expect("to parse circle");
+        assert!(circle.contains(P2::new(0.7, 0.7), FillRule::NonZero));
+        assert!(!circle.contains(P2::new(0.72, 0.72), FillRule::NonZero));
+    }
+
+    #[test]
+    fn closest_point() {
+        assert_eq!(Path::new().closest_point(P2::new(1., 1.)), None);
+
+        let square = Path::from_svg("M0 0 H1 V1 H0 Z").expect("to parse square");
+        assert_eq!(
+            square.closest_point(P2::new(0.5, -2.)),
+            Some((P2::new(0.5, 0.), 2.))
+        );
+        assert_eq!(
+            square.closest_point(P2::new(0.25, 0.5)),
+            Some((P2::new(0., 0.5), 0.25))
+        );
+        assert_eq!(
+            square.closest_point(P2::new(2., 2.)),
+            Some((P2::new(1., 1.), 2f32.sqrt()))
+        );
+
+        let arc = Path::from_svg("M1 0 A1 1 0 0 1 0 1").expect("to parse arc");
+        let (nearest, distance) = arc
+            .closest_point(P2::new(2., 2.))
+            .expect("arc closest point");
+        let expected = P2::new(0.5f32.sqrt(), 0.5f32.sqrt());
+        assert!((nearest - expected).length() < 0.005);
+        assert!((distance - (8f32.sqrt() - 1.)).abs() < 0.005);
+
+        let mut bulge = Path::new();
+        bulge.move_to(P2::new(0., 0.));
+        bulge.quadratic_to(P2::new(1., 2.), P2::new(2., 0.));
+        let (nearest, distance) = bulge
+            .closest_point(P2::new(1., 3.))
+            .expect("bulge closest point");
+        assert_near(nearest, P2::new(1., 1.));
+        assert!((distance - 2.).abs() < 0.0001);
+    }
+
+    fn subpath_ends(path: &Path) -> Vec<(P2, P2)> {
+        path.events()
+            .filter_map(|event| match event {
+                PathEvent::End { first, last, .. } => Some((first, last)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dash() {
+        let line = Path::from_svg("M0 0 H10").expect("to parse line");
+        let dashes = line.dash(&DashPattern::new(vec![2., 2.]));
+        let expected = [(0., 2.), (4., 6.), (8., 10.)];
+        let ends = subpath_ends(&dashes);
+        assert_eq!(ends.len(), expected.len());
+        ends.iter()
+            .zip(expected.iter())
+            .for_each(|((first, last), (start, end))| {
+                assert_near(*first, P2::new(*start, 0.));
+                assert_near(*last, P2::new(*end, 0.));
+            });
+
+        let offset = line.dash(&DashPattern::new(vec![2., 2.]).with_offset(3.));
+        let ends = subpath_ends(&offset);
+        assert_eq!(ends.len(), 3);
+        assert_near(ends[0].0, P2::new(1., 0.));
+        assert_near(ends[0].1, P2::new(3., 0.));
+        assert_near(ends[2].0, P2::new(9., 0.));
+
+        // Dashes continue around corners and along the closing edge.
+        let square = Path::from_svg("M0 0 H2 V2 H0 Z").expect("to parse square");
+        let ends = subpath_ends(&square.dash(&DashPattern::new(vec![3.])));
+        assert_eq!(ends.len(), 2);
+        assert_near(ends[0].1, P2::new(2., 1.));
+        assert_near(ends[1].0, P2::new(0., 2.));
+        assert_near(ends[1].1, P2::new(0., 0.));
+
+        assert_eq!(line.dash(&DashPattern::new(vec![])), line);
+    }
+
+    #[test]
+    fn dash_curve() {
+        let arc = Path::from_svg("M1 0 A1 1 0 0 1 -1 0").expect("to parse arc");
+        let quarter = std::f32::consts::PI / 4.;
+        let dashes = arc.dash(&DashPattern::new(vec![quarter, quarter]));
+        let ends = subpath_ends(&dashes);
+        assert_eq!(ends.len(), 2);
+
+        let on_circle = |p: P2| (p.to_vector().length() - 1.).abs() < 0.005;
+        assert!(ends.iter().all(|(a, b)| on_circle(*a) && on_circle(*b)));
+        let expected_end = P2::new(0.5f32.sqrt(), 0.5f32.sqrt());
+        assert!((ends[0].1 - expected_end).length() < 0.01);
+    }
+
+    fn vertices(path: &Path) -> Vec<P2> {
+        path.events()
+            .filter_map(|event| match event {
+                PathEvent::Begin { at } => Some(at),
+                PathEvent::Line { to, .. } => Some(to),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn variable_stroke() {
+        let line = Path::from_svg("M0 0 H10").expect("to parse line");
+        let outline = line.variable_stroke(|t| 1. + 2. * t);
+        let trapezoid = vertices(&outline);
+        assert_eq!(trapezoid.len(), 4);
+        assert_near(trapezoid[0], P2::new(0., 1.));
+        assert_near(trapezoid[1], P2::new(10., 3.));
+        assert_near(trapezoid[2], P2::new(10., -3.));
+        assert_near(trapezoid[3], P2::new(0., -1.));
+        assert!(outline.contains(P2::new(9., 2.5), FillRule::EvenOdd));
+        assert!(!outline.contains(P2::new(1., 2.5), FillRule::EvenOdd));
+
+        let square = Path::from_svg("M0 0 H4 V4 H0 Z").expect("to parse square");
+        let band = square.variable_stroke(|_| 0.5);
+        assert_eq!(subpath_ends(&band).len(), 2);
+        assert!(band.contains(P2::new(2., 0.25), FillRule::EvenOdd));
+        assert!(!band.contains(P2::new(2., 2.), FillRule::EvenOdd));
+        assert!(!band.contains(P2::new(2., -1.), FillRule::EvenOdd));
+
+        let hairpin = Path::from_svg("M0 0 H10 H0").expect("to parse hairpin");
+        let outline = hairpin.variable_stroke(|_| 1.);
+        let points = vertices(&outline);
+        assert_eq!(points.len(), 6);
+        assert!(points.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+        assert_near(points[1], P2::new(10., 1.));
+        assert_near(points[4], P2::new(10., -1.));
+    }
+
+    #[test]
+    fn winding_at() {
+        let square = Path::from_svg("M0 0 H4 V4 H0 Z").expect("to parse square");
+        let reversed = Path::from_svg("M0 0 V4 H4 V0 Z").expect("to parse reversed square");
+        let inside = P2::new(2., 2.);
+        assert_eq!(square.winding_at(inside), 1);
+        assert_eq!(reversed.winding_at(inside), -square.winding_at(inside));
+        assert_eq!(square.winding_at(P2::new(5., 2.)), 0);
+
+        let mut twice = square.clone();
+        twice.move_to(P2::new(1., 1.));
+        twice.line_to(P2::new(3., 1.));
+        twice.line_to(P2::new(3., 3.));
+        twice.line_to(P2::new(1., 3.));
+        twice.close_path();
+        assert_eq!(twice.winding_at(inside), 2 * square.winding_at(inside));
+        assert_eq!(
+            twice.winding_at(P2::new(0.5, 2.)),
+            square.winding_at(inside)
+        );
+        assert_eq!(twice.winding_at(P2::new(-1., 2.)), 0);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut path = Path::new();
+        path.move_to(P2::new(0., 0.));
+        path.cubic_to(P2::new(1., 2.), P2::new(3., 2.), P2::new(4., 0.));
+        path.line_to(P2::new(5., 1.));
+        path.move_to(P2::new(10., 0.));
+        path.quadratic_to(P2::new(11., 1.), P2::new(12., 0.));
+        path.close_path();
+
+        let reversed = path.reverse();
+        let mut expected = Path::new();
+        expected.move_to(P2::new(12., 0.));
+        expected.quadratic_to(P2::new(11., 1.), P2::new(10., 0.));
+        expected.close_path();
+        expected.move_to(P2::new(5., 1.));
+        expected.line_to(P2::new(4., 0.));
+        expected.cubic_to(P2::new(3., 2.), P2::new(1., 2.), P2::new(0., 0.));
+        assert_eq!(reversed, expected);
+        assert_eq!(reversed.reverse(), path);
+
+        let square = Path::from_svg("M0 0 H4 V4 H0 Z").expect("to parse square");
+        let inside = P2::new(2., 2.);
+        assert_eq!(
+            square.reverse().winding_at(inside),
+            -square.winding_at(inside)
+        );
+    }
+
+    #[test]
+    fn append() {
+        let left = Path::from_svg("M0 0 L4 0 L2 4 Z").expect("to parse left triangle");
+        let right = Path::from_svg("M10 0 L14 0 L12 4 Z").expect("to parse right triangle");
+
+        let mut both = left.clone();
+        both.append(&right);
+        assert_eq!(
+            subpath_ends(&both),
+            vec![
+                (P2::new(0., 0.), P2::new(2., 4.)),
+                (P2::new(10., 0.), P2::new(12., 4.))
+            ]
+        );
+        assert!(both.contains(P2::new(2., 1.), FillRule::NonZero));
+        assert!(both.contains(P2::new(12., 1.), FillRule::NonZero));
+        assert!(!both.contains(P2::new(7., 1.), FillRule::NonZero));
+
+        let mut empty = Path::new();
+        empty.append(&left);
+        assert_eq!(empty, left);
+    }
+
+    #[test]
+    fn point_at_length() {
+        let line = Path::from_svg("M0 0 H10").expect("to parse line");
+        assert_eq!(line.total_length(), 10.);
+        assert_eq!(line.point_at_length(2.5), Some(P2::new(2.5, 0.)));
+        assert_eq!(line.point_at_length(-1.), Some(P2::new(0., 0.)));
+        assert_eq!(line.point_at_length(20.), Some(P2::new(10., 0.)));
+        assert_eq!(Path::new().point_at_length(1.), None);
+
+        let square = Path::from_svg("M0 0 H2 V2 H0 Z").expect("to parse square");
+        assert_eq!(square.total_length(), 8.);
+        assert_eq!(square.point_at_length(7.), Some(P2::new(0., 1.)));
+
+        // The curve moves evenly in x with t but climbs steeply near its ends, so equal steps
+        // in length are not equal steps in t.
+        let hump = Path::from_svg("M0 0 Q5 10 10 0").expect("to parse curve");
+        let total = hump.total_length();
+        let p = hump
+            .point_at_length(total / 4.)
+            .expect("a point on the curve");
+        let t = p.x / 10.;
+        assert!(p.x < 2.4);
+        assert!((p.y - 20. * t * (1. - t)).abs() < 0.01);
+        let middle = hump
+            .point_at_length(total / 2.)
+            .expect("a point on the curve");
+        assert!((middle - P2::new(5., 5.)).length() < 0.01);
+    }
+
+    #[test]
+    fn tangent_at_length() {
+        let corner = Path::from_svg("M0 0 H4 H4 V4").expect("to parse corner");
+        let (right, down) = (V2::new(1., 0.), V2::new(0., 1.));
+        assert_eq!(corner.tangent_at_length(0.), Some(right));
+        assert_eq!(corner.tangent_at_length(2.), Some(right));
+        assert_eq!(corner.tangent_at_length(4.), Some(right));
+        assert_eq!(corner.tangent_at_length(6.), Some(down));
+        assert_eq!(corner.tangent_at_length(10.), Some(down));
+
+        for length in &[1., 4., 7.] {
+            let tangent = corner.tangent_at_length(*length).expect("a tangent");
+            let normal = corner.normal_at_length(*length).expect("a normal");
+            assert_eq!(tangent.dot(normal), 0.);
+            assert_eq!(normal.length(), 1.);
+        }
+        assert_eq!(corner.normal_at_length(1.), Some(down));
+
+        let curve = Path::from_svg("M0 0 C0 0 4 4 4 0").expect("to parse curve");
+        let start = curve.tangent_at_length(0.).expect("a tangent");
+        assert!(start.x > 0. && start.y > 0.);
+        assert_eq!(Path::from_svg("M1 1").unwrap().tangent_at_length(0.), None);
+    }
+
+    #[test]
+    fn validate() {
+        let mut path = Path::from_svg("M0 0 L1 1").expect("to parse line");
+        assert_eq!(path.validate(), Ok(()));
+
+        path.line_to(P2::new(std::f32::NAN, 0.));
+        assert_eq!(path.validate(), Err(PathError::NonFiniteCoordinate(2)));
+
+        let mut path = Path::new();
+        path.move_to(P2::new(0., std::f32::INFINITY));
+        assert_eq!(path.validate(), Err(PathError::NonFiniteCoordinate(0)));
+    }
+
+    #[test]
+    fn outline_stroke() {
+        const MITER_LIMIT: f32 = StrokeOptions::DEFAULT_MITER_LIMIT;
+
+        let line = Path::from_svg("M0 0 H10").expect("to parse line");
+        let outline = line.outline_stroke(2., LineCap::Butt, LineJoin::Miter, MITER_LIMIT);
+        let mut corners = vertices(&outline)
+            .into_iter()
+            .map(|p| (p.x, p.y))
+            .collect::<Vec<_>>();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(corners, vec![(0., -1.), (0., 1.), (10., -1.), (10., 1.)]);
+        assert_eq!(subpath_ends(&outline).len(), 1);
+
+        let covers = |path: &Path, x: f32, y: f32| path.contains(P2::new(x, y), FillRule::NonZero);
+        let square = line.outline_stroke(2., LineCap::Square, LineJoin::Miter, MITER_LIMIT);
+        assert!(covers(&square, -0.9, 0.9) && covers(&square, 10.9, -0.9));
+        let round = line.outline_stroke(2., LineCap::Round, LineJoin::Miter, MITER_LIMIT);
+        assert!(covers(&round, -0.9, 0.) && !covers(&round, -0.9, 0.9));
+
+        let corner = Path::from_svg("M0 0 H10 V10").expect("to parse corner");
+        let miter = corner.outline_stroke(2., LineCap::Butt, LineJoin::Miter, MITER_LIMIT);
+        let bevel = corner.outline_stroke(2., LineCap::Butt, LineJoin::Bevel, MITER_LIMIT);
+        let round = corner.outline_stroke(2., LineCap::Butt, LineJoin::Round, MITER_LIMIT);
+        assert!(covers(&miter, 10.9, -0.9));
+        assert!(!covers(&bevel, 10.9, -0.9) && covers(&bevel, 10.4, -0.4));
+        assert!(!covers(&round, 10.9, -0.9) && covers(&round, 10.6, -0.6));
+        for outline in &[&miter, &bevel, &round] {
+            assert!(covers(outline, 5., 0.9) && covers(outline, 9.5, 0.5));
+            assert!(!covers(outline, 5., 1.1) && !covers(outline, 8.9, 2.));
+        }
+
+        let hairpin = Path::from_svg("M0 0 H10 H0").expect("to parse hairpin");
+        let round = hairpin.outline_stroke(2., LineCap::Butt, LineJoin::Round, MITER_LIMIT);
+        let miter = hairpin.outline_stroke(2., LineCap::Butt, LineJoin::Miter, MITER_LIMIT);
+        assert!(covers(&round, 10.9, 0.) && covers(&round, 10.6, 0.6));
+        assert!(!covers(&round, 10.9, 0.9));
+        assert!(!covers(&miter, 10.1, 0.) && covers(&miter, 9.9, 0.9));
+
+        // A miter past the limit on a sharp spike falls back to a bevel.
+        let spike = Path::from_svg("M0 0 L10 1 L0 2").expect("to parse spike");
+        let reach = |miter_limit| {
+            spike
+                .outline_stroke(2., LineCap::Butt, LineJoin::Miter, miter_limit)
+                .bounds()
+                .expect("an outline")
+                .max_x()
+        };
+        assert!(reach(StrokeOptions::DEFAULT_MITER_LIMIT) < 11.5);
+        assert!(reach(20.) > 19.);
+    }
+
+    #[test]
+    fn smooth_through() {
+        let points = [
+            P2::new(0., 0.),
+            P2::new(3., 4.),
+            P2::new(6., -1.),
+            P2::new(10., 2.),
+        ];
+        let curve = Path::smooth_through(&points, 0.5);
+        let flattened = curve.flattened_subpaths(0.01);
+        assert_eq!(flattened.len(), 1);
+        for p in &points {
+            assert!(flattened[0].iter().any(|q| (*q - *p).length() < 0.001));
+        }
+        assert_eq!(curve.current_point(), Some(P2::new(10., 2.)));
+
+        // The curve is smooth, so the tangent is the same on both sides of each point.
+        let segments = curve.segments().collect::<Vec<_>>();
+        for pair in segments.windows(2) {
+            let incoming = derivative(&pair[0], 1.).normalize();
+            let outgoing = derivative(&pair[1], 0.).normalize();
+            assert!((incoming - outgoing).length() < 0.001);
+        }
+
+        let straight = Path::smooth_through(&points[..2], 0.5);
+        assert!(straight.closest_point(P2::new(1.5, 2.)).unwrap().1 < 0.001);
+        assert_eq!(Path::smooth_through(&[], 0.5), Path::new());
+    }
+
+    #[test]
+    fn from_events() {
+        let mut builder = lyon_path::Builder::new();
+        builder.move_to(P2::new(0., 0.));
+        builder.line_to(P2::new(4., 0.));
+        builder.quadratic_bezier_to(P2::new(4., 2.), P2::new(2., 4.));
+        builder.close();
+        let lyon_path = builder.build();
+
+        let mut expected = Path::new();
+        expected.move_to(P2::new(0., 0.));
+        expected.line_to(P2::new(4., 0.));
+        expected.quadratic_to(P2::new(4., 2.), P2::new(2., 4.));
+        expected.close_path();
+
+        assert_eq!(Path::from_events(lyon_path.iter()), expected);
+        assert_eq!(Path::from_events(expected.events()), expected);
+    }
+
+    #[test]
+    fn svg_errors() {
+        assert_eq!(Path::from_svg("L1 1"), Err(ParseError::MissingMoveTo));
+        assert_eq!(
+            Path::from_svg("M0 0 X1 1"),
+            Err(ParseError::UnsupportedCommand('X', 5))
+        );
+        assert_eq!(
+            Path::from_svg("M0 0 L1"),
+            Err(ParseError::ExpectedNumber(7))
+        );
+        assert_eq!(
+            Path::from_svg("M0 0 #"),
+            Err(ParseError::UnexpectedCharacter('#', 5))
+        );
+    }
+}
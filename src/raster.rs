@@ -1,12 +1,14 @@
 //! Path rasterization.
 
-use crate::{gpu::GpuVertex, Result, P2};
-use lyon_path::Builder;
+use crate::{gpu::GpuVertex, path::validate_events, Result, P2};
+use euclid::default::Rect;
+use lyon_path::{Builder, Path, PathEvent};
 use lyon_tessellation::{
-    BuffersBuilder, FillAttributes, FillOptions, FillTessellator, StrokeAttributes, StrokeOptions,
-    StrokeTessellator, VertexBuffers,
+    BuffersBuilder, FillAttributes, FillOptions, FillRule, FillTessellator, LineCap, LineJoin,
+    StrokeAttributes, StrokeOptions, StrokeTessellator, VertexBuffers,
 };
 use palette::LinSrgba;
+use rayon::prelude::*;
 
 /// The method by which the rasterizer will rasterize the vector path.
 #[derive(Debug, Clone, Copy)]
@@ -14,28 +16,65 @@ pub enum Method {
     /// In fill method, the rasterizer will treat all the area inside the path as part of the
     /// raster area. In this method, paths are automatically closed by assuming an edge from the
     /// last to the first vertex.
-    Fill,
+    ///
+    /// The fill rule decides which regions are inside when the path overlaps itself or contains
+    /// multiple subpaths.
+    Fill(FillRule),
     /// In stroke method, the rasterizer will treat the area immediately adjacent the path within
     /// the given width as part of the rastered area. In this method, paths are left open
     /// and no edge between the last and first vertex is assumed.
-    Stroke(f32),
+    Stroke(Stroke),
 }
 
+/// The style of a stroke.
+#[derive(Debug, Clone, Copy)]
+pub struct Stroke {
+    /// The width of the stroke.
+    pub width: f32,
+    /// The shape drawn at the ends of open paths.
+    pub line_cap: LineCap,
+    /// The shape used where two segments of the path meet.
+    pub line_join: LineJoin,
+    /// The limit on the ratio of miter length to stroke width, past which miter joins are
+    /// drawn as bevels. Must be at least 1.
+    pub miter_limit: f32,
+    /// Whether open subpaths which end within the tolerance of where they start are stroked as
+    /// closed, joining their ends instead of capping them.
+    pub auto_close: bool,
+}
+
+/// The default maximum distance, in pixels, between a curve and the line segments which
+/// approximate it.
+pub const DEFAULT_TOLERANCE: f32 = 0.05;
+
+/// The smallest tolerance curves are flattened with. Curves are flattened in steps which shrink
+/// with the tolerance, so a tolerance of zero would never finish.
+pub const MIN_TOLERANCE: f32 = 0.001;
+
+/// Rasters a path, flattening curves into line segments which stray at most `tolerance` pixels
+/// from the curve.
+///
+/// Paths with NaN or infinite coordinates are rejected with a `PathError`.
 pub fn raster_path(
-    builder: Builder,
+    path: &Path,
     method: Method,
     color: LinSrgba,
+    tolerance: f32,
 ) -> Result<(Vec<GpuVertex>, Vec<u32>)> {
+    validate_events(path.iter())?;
+
     match method {
-        Method::Fill => {
+        Method::Fill(fill_rule) => {
             let ctor = |v: P2, _: FillAttributes| -> P2 { v };
             let mut buffers: VertexBuffers<P2, u32> = VertexBuffers::new();
             let mut buffers_builder = BuffersBuilder::new(&mut buffers, ctor);
 
             let mut tessellator = FillTessellator::new();
             let result = tessellator.tessellate_path(
-                &builder.build(),
-                &FillOptions::default().with_tolerance(0.05),
+                path,
+                &FillOptions::default()
+                    .with_tolerance(tolerance)
+                    .with_fill_rule(fill_rule),
                 &mut buffers_builder,
             );
             match result {
@@ -60,7 +99,14 @@ pub fn raster_path(
                 buffers.indices,
             ))
         }
-        Method::Stroke(width) => {
+        Method::Stroke(stroke) => {
+            let closed = if stroke.auto_close {
+                Some(close_nearly_closed(path, tolerance))
+            } else {
+                None
+            };
+            let path = closed.as_ref().unwrap_or(path);
+
             let ctor = |v: P2, _: StrokeAttributes| -> P2 { v };
             let mut buffers: VertexBuffers<P2, u32> = VertexBuffers::new();
             let mut buffers_builder = BuffersBuilder::new(&mut buffers, ctor);
@@ -68,10 +114,13 @@ pub fn raster_path(
             let mut tessellator = StrokeTessellator::new();
             tessellator
                 .tessellate_path(
-                    &builder.build(),
+                    path,
                     &StrokeOptions::default()
-                        .with_line_width(width)
-                        .with_tolerance(0.05),
+                        .with_line_width(stroke.width)
+                        .with_line_cap(stroke.line_cap)
+                        .with_line_join(stroke.line_join)
+                        .with_miter_limit(stroke.miter_limit)
+                        .with_tolerance(tolerance),
                     &mut buffers_builder,
                 )
                 .expect("TODO: wrap error");
@@ -95,3 +144,527 @@ pub fn raster_path(
         }
     }
 }
+
+/// Returns the path with each open subpath whose ends lie within `distance` of each other
+/// closed, moving its end onto its start.
+fn close_nearly_closed(path: &Path, distance: f32) -> Path {
+    let mut builder = Builder::new();
+    let mut segments = vec![];
+    for event in path.iter() {
+        match event {
+            PathEvent::Begin { at } => builder.move_to(at),
+            PathEvent::End { last, first, close } => {
+                let snap = !close && segments.len() > 1 && (last - first).length() <= distance;
+                if snap {
+                    // The closing edge replaces a final line; a final curve is bent to end at
+                    // the start instead.
+                    match segments.last_mut() {
+                        Some(PathEvent::Line { .. }) => {
+                            segments.pop();
+                        }
+                        Some(PathEvent::Quadratic { to, .. })
+                        | Some(PathEvent::Cubic { to, .. }) => *to = first,
+                        _ => {}
+                    }
+                }
+
+                for segment in segments.drain(..) {
+                    match segment {
+                        PathEvent::Line { to, .. } => builder.line_to(to),
+                        PathEvent::Quadratic { ctrl, to, .. } => {
+                            builder.quadratic_bezier_to(ctrl, to)
+                        }
+                        PathEvent::Cubic {
+                            ctrl1, ctrl2, to, ..
+                        } => builder.cubic_bezier_to(ctrl1, ctrl2, to),
+                        _ => {}
+                    }
+                }
+                if close || snap {
+                    builder.close();
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    builder.build()
+}
+
+/// Rasters each path in parallel and merges the results into one set of buffers, in the order
+/// the paths were given.
+///
+/// If a viewport is given, paths which lie entirely outside it are skipped. Pass `None` when the
+/// paths will not be drawn where they lie, such as under a custom vertex shader.
+pub fn raster_paths(
+    paths: Vec<(Path, Method, LinSrgba, f32)>,
+    viewport: Option<Rect<f32>>,
+) -> Result<(Vec<GpuVertex>, Vec<u32>)> {
+    let rastered = paths
+        .into_par_iter()
+        .map(|(path, method, color, tolerance)| {
+            if let Some(viewport) = viewport {
+                if !visible(&path, method, viewport) {
+                    return Ok((vec![], vec![]));
+                }
+            }
+            raster_path(&path, method, color, tolerance)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(rastered.into_iter().fold(
+        (vec![], vec![]),
+        |(mut vertices, mut indices), (mut new_vertices, new_indices)| {
+            let idx = vertices.len() as u32;
+            vertices.append(&mut new_vertices);
+            indices.extend(new_indices.into_iter().map(|i| i + idx));
+            (vertices, indices)
+        },
+    ))
+}
+
+/// Returns false if the rastered path certainly lies outside the viewport.
+fn visible(path: &Path, method: Method, viewport: Rect<f32>) -> bool {
+    match raster_bounds(path, method) {
+        Some(bounds) => bounds.intersects(&viewport),
+        None => false,
+    }
+}
+
+/// Returns a rectangle containing everything the path rasters with the given method, or `None`
+/// if the path is empty.
+///
+/// Curves lie inside the hull of their control points, so the bounds of all points in the path,
+/// widened by the farthest a stroke can reach past them, contain everything the path rasters.
+/// A stroke reaches half its width past the path, or further at miter joins and square caps.
+pub fn raster_bounds(path: &Path, method: Method) -> Option<Rect<f32>> {
+    let mut points = path
+        .iter()
+        .flat_map(|event| -> Vec<P2> {
+            match event {
+                PathEvent::Begin { at } => vec![at],
+                PathEvent::Line { to, .. } => vec![to],
+                PathEvent::Quadratic { ctrl, to, .. } => vec![ctrl, to],
+                PathEvent::Cubic {
+                    ctrl1, ctrl2, to, ..
+                } => vec![ctrl1, ctrl2, to],
+                PathEvent::End { .. } => vec![],
+            }
+        })
+        .peekable();
+    if points.peek().is_none() {
+        return None;
+    }
+    let bounds = Rect::from_points(points);
+
+    let reach = match method {
+        Method::Fill(_) => 0.,
+        Method::Stroke(stroke) => {
+            let cap = match stroke.line_cap {
+                LineCap::Square => std::f32::consts::SQRT_2,
+                _ => 1.,
+            };
+            let join = match stroke.line_join {
+                LineJoin::Miter | LineJoin::MiterClip => stroke.miter_limit,
+                _ => 1.,
+            };
+            stroke.width / 2. * cap.max(join).max(1.)
+        }
+    };
+    Some(bounds.inflate(reach, reach))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn polygon(builder: &mut Builder, vertices: &[P2]) {
+        builder.move_to(vertices[0]);
+        vertices[1..].iter().for_each(|v| builder.line_to(*v));
+        builder.close();
+    }
+
+    fn square(builder: &mut Builder, min: f32, max: f32, clockwise: bool) {
+        let mut corners = vec![
+            P2::new(min, min),
+            P2::new(max, min),
+            P2::new(max, max),
+            P2::new(min, max),
+        ];
+        if !clockwise {
+            corners.reverse();
+        }
+        polygon(builder, &corners);
+    }
+
+    fn covers(vertices: &[GpuVertex], indices: &[u32], p: P2) -> bool {
+        indices.chunks(3).any(|triangle| {
+            let [a, b, c] = [
+                vertices[triangle[0] as usize].vpos,
+                vertices[triangle[1] as usize].vpos,
+                vertices[triangle[2] as usize].vpos,
+            ];
+            let side = |u: [f32; 2], v: [f32; 2]| {
+                (v[0] - u[0]) * (p.y - u[1]) - (v[1] - u[1]) * (p.x - u[0])
+            };
+            let (d0, d1, d2) = (side(a, b), side(b, c), side(c, a));
+            let has_neg = d0 < 0. || d1 < 0. || d2 < 0.;
+            let has_pos = d0 > 0. || d1 > 0. || d2 > 0.;
+            !(has_neg && has_pos)
+        })
+    }
+
+    fn fill_annulus(fill_rule: FillRule, inner_clockwise: bool) -> (Vec<GpuVertex>, Vec<u32>) {
+        let mut builder = Builder::new();
+        square(&mut builder, 0., 4., /*clockwise=*/ true);
+        square(&mut builder, 1., 3., inner_clockwise);
+        raster_path(
+            &builder.build(),
+            Method::Fill(fill_rule),
+            LinSrgba::new(1., 1., 1., 1.),
+            DEFAULT_TOLERANCE,
+        )
+        .expect("to raster annulus")
+    }
+
+    #[test]
+    fn annulus_even_odd() {
+        let (vertices, indices) = fill_annulus(FillRule::EvenOdd, /*inner_clockwise=*/ true);
+        assert!(!covers(&vertices, &indices, P2::new(2., 2.)));
+        assert!(covers(&vertices, &indices, P2::new(0.5, 2.)));
+    }
+
+    #[test]
+    fn annulus_non_zero() {
+        let (vertices, indices) = fill_annulus(FillRule::NonZero, /*inner_clockwise=*/ false);
+        assert!(!covers(&vertices, &indices, P2::new(2., 2.)));
+        assert!(covers(&vertices, &indices, P2::new(0.5, 2.)));
+
+        let (vertices, indices) = fill_annulus(FillRule::NonZero, /*inner_clockwise=*/ true);
+        assert!(covers(&vertices, &indices, P2::new(2., 2.)));
+    }
+
+    #[test]
+    fn overlapping_triangles_winding() {
+        let left = [P2::new(0., 0.), P2::new(4., 0.), P2::new(2., 4.)];
+        let right = [P2::new(1., 0.), P2::new(5., 0.), P2::new(3., 4.)];
+        let overlap = P2::new(2.5, 1.);
+
+        let fill = |fill_rule: FillRule, same_direction: bool| {
+            let mut builder = Builder::new();
+            polygon(&mut builder, &left);
+            if same_direction {
+                polygon(&mut builder, &right);
+            } else {
+                let mut reversed = right;
+                reversed.reverse();
+                polygon(&mut builder, &reversed);
+            }
+            let (vertices, indices) = raster_path(
+                &builder.build(),
+                Method::Fill(fill_rule),
+                LinSrgba::new(1., 1., 1., 1.),
+                DEFAULT_TOLERANCE,
+            )
+            .expect("to raster triangles");
+            covers(&vertices, &indices, overlap)
+        };
+
+        assert!(fill(FillRule::NonZero, /*same_direction=*/ true));
+        assert!(!fill(FillRule::EvenOdd, /*same_direction=*/ true));
+        assert!(!fill(FillRule::NonZero, /*same_direction=*/ false));
+        assert!(!fill(FillRule::EvenOdd, /*same_direction=*/ false));
+    }
+
+    fn bounds(vertices: &[GpuVertex]) -> (P2, P2) {
+        vertices.iter().fold(
+            (
+                P2::new(std::f32::MAX, std::f32::MAX),
+                P2::new(std::f32::MIN, std::f32::MIN),
+            ),
+            |(min, max), v| (min.min(v.vpos.into()), max.max(v.vpos.into())),
+        )
+    }
+
+    fn miter_stroke(width: f32) -> Stroke {
+        Stroke {
+            width,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+            auto_close: false,
+        }
+    }
+
+    fn assert_near(a: P2, b: P2) {
+        assert!((a - b).length() < 0.001, "{:?} is not near {:?}", a, b);
+    }
+
+    fn stroke_polyline(vertices: &[P2], stroke: Stroke) -> (Vec<GpuVertex>, Vec<u32>) {
+        let mut builder = Builder::new();
+        builder.move_to(vertices[0]);
+        vertices[1..].iter().for_each(|v| builder.line_to(*v));
+        raster_path(
+            &builder.build(),
+            Method::Stroke(stroke),
+            LinSrgba::new(1., 1., 1., 1.),
+            DEFAULT_TOLERANCE,
+        )
+        .expect("to raster stroke")
+    }
+
+    #[test]
+    fn stroke_line() {
+        let (vertices, _) = stroke_polyline(&[P2::new(0., 0.), P2::new(10., 0.)], miter_stroke(2.));
+        let (min, max) = bounds(&vertices);
+        assert_near(min, P2::new(0., -1.));
+        assert_near(max, P2::new(10., 1.));
+    }
+
+    #[test]
+    fn stroke_corner() {
+        let corner = [P2::new(0., 0.), P2::new(10., 0.), P2::new(10., 10.)];
+
+        let (vertices, indices) = stroke_polyline(&corner, miter_stroke(2.));
+        let (min, max) = bounds(&vertices);
+        assert_near(min, P2::new(0., -1.));
+        assert_near(max, P2::new(11., 10.));
+        assert!(covers(&vertices, &indices, P2::new(10.9, -0.9)));
+
+        let (vertices, indices) = stroke_polyline(
+            &corner,
+            Stroke {
+                line_join: LineJoin::Bevel,
+                ..miter_stroke(2.)
+            },
+        );
+        assert!(!covers(&vertices, &indices, P2::new(10.9, -0.9)));
+    }
+
+    #[test]
+    fn stroke_miter_limit() {
+        // The arms meet at about 11 degrees, so the miter reaches about 10 widths past the tip.
+        let spike = [P2::new(0., 0.), P2::new(10., 1.), P2::new(0., 2.)];
+        let reach = |miter_limit| {
+            let (vertices, _) = stroke_polyline(
+                &spike,
+                Stroke {
+                    miter_limit,
+                    ..miter_stroke(2.)
+                },
+            );
+            bounds(&vertices).1.x
+        };
+
+        assert!(reach(20.) > 19.);
+        assert!(reach(2.) < 12.);
+        assert!(reach(StrokeOptions::DEFAULT_MITER_LIMIT) < 14.);
+    }
+
+    #[test]
+    fn stroke_caps() {
+        let line = [P2::new(0., 0.), P2::new(10., 0.)];
+        let extent = |line_cap| {
+            let (vertices, _) = stroke_polyline(
+                &line,
+                Stroke {
+                    line_cap,
+                    ..miter_stroke(2.)
+                },
+            );
+            let (min, max) = bounds(&vertices);
+            max - min
+        };
+
+        let butt = extent(LineCap::Butt);
+        let square = extent(LineCap::Square);
+        assert!((square.x - butt.x - 2.).abs() < 0.001);
+        assert!((square.y - butt.y).abs() < 0.001);
+    }
+
+    #[test]
+    fn stroke_auto_close() {
+        // The triangle ends just short of where it starts, so its first corner is either joined
+        // with a miter or left as two butt caps which do not reach the miter's tip.
+        let triangle = [
+            P2::new(0., 0.),
+            P2::new(10., 0.),
+            P2::new(5., 8.),
+            P2::new(0.01, 0.),
+        ];
+        let miter_tip = P2::new(-1.2, -0.8);
+
+        let auto_close = Stroke {
+            auto_close: true,
+            ..miter_stroke(2.)
+        };
+
+        let (vertices, indices) = stroke_polyline(&triangle, auto_close);
+        assert!(covers(&vertices, &indices, miter_tip));
+
+        let (vertices, indices) = stroke_polyline(&triangle, miter_stroke(2.));
+        assert!(!covers(&vertices, &indices, miter_tip));
+
+        let (vertices, indices) = stroke_polyline(
+            &[
+                P2::new(0., 0.),
+                P2::new(10., 0.),
+                P2::new(5., 8.),
+                P2::new(1., 0.),
+            ],
+            auto_close,
+        );
+        assert!(!covers(&vertices, &indices, miter_tip));
+    }
+
+    fn viewport(size: f32) -> Rect<f32> {
+        Rect::new(P2::origin(), euclid::default::Size2D::new(size, size))
+    }
+
+    #[test]
+    fn raster_paths_culls_offscreen_paths() {
+        let raster_in = |min: f32, method: Method, viewport: Option<Rect<f32>>| {
+            let mut builder = Builder::new();
+            square(&mut builder, min, min + 2., /*clockwise=*/ true);
+            raster_paths(
+                vec![(
+                    builder.build(),
+                    method,
+                    LinSrgba::new(1., 1., 1., 1.),
+                    DEFAULT_TOLERANCE,
+                )],
+                viewport,
+            )
+            .expect("to raster square")
+        };
+        let raster = |min: f32, method: Method| raster_in(min, method, Some(viewport(10.)));
+        let fill = Method::Fill(FillRule::EvenOdd);
+
+        assert!(raster(-5., fill).0.is_empty());
+        assert!(raster(20., fill).0.is_empty());
+        assert!(!raster(-1., fill).0.is_empty());
+        assert!(!raster(4., fill).0.is_empty());
+
+        // A stroke reaches past its path, so it is kept while its edge may be visible.
+        assert!(!raster(-2.5, Method::Stroke(miter_stroke(2.))).0.is_empty());
+        assert!(raster(-10., Method::Stroke(miter_stroke(2.))).0.is_empty());
+
+        // Without a viewport, as for custom vertex shaders, nothing is culled.
+        assert!(!raster_in(-5., fill, None).0.is_empty());
+        assert!(!raster_in(20., fill, None).0.is_empty());
+    }
+
+    #[test]
+    fn raster_bounds_of_strokes() {
+        let mut builder = Builder::new();
+        square(&mut builder, 0., 10., /*clockwise=*/ true);
+        let path = builder.build();
+        let bounds_of = |method| raster_bounds(&path, method).expect("bounds of a square");
+
+        let fill = bounds_of(Method::Fill(FillRule::EvenOdd));
+        assert_eq!(
+            fill,
+            Rect::from_points(&[P2::new(0., 0.), P2::new(10., 10.)])
+        );
+
+        let round = bounds_of(Method::Stroke(Stroke {
+            line_join: LineJoin::Round,
+            ..miter_stroke(2.)
+        }));
+        assert_eq!(round, fill.inflate(1., 1.));
+
+        let miter = bounds_of(Method::Stroke(miter_stroke(2.)));
+        assert_eq!(miter, fill.inflate(4., 4.));
+
+        let (vertices, _) = stroke_polyline(
+            &[
+                P2::new(0., 0.),
+                P2::new(10., 0.),
+                P2::new(10., 10.),
+                P2::new(0., 10.),
+                P2::new(0., 0.),
+            ],
+            miter_stroke(2.),
+        );
+        let (min, max) = bounds(&vertices);
+        assert!(miter.contains_rect(&Rect::from_points(&[min, max])));
+
+        let empty = Builder::new().build();
+        assert_eq!(raster_bounds(&empty, Method::Fill(FillRule::EvenOdd)), None);
+    }
+
+    #[test]
+    fn raster_paths_preserves_order() {
+        let paths = || {
+            (0..32).map(|i| {
+                let mut builder = Builder::new();
+                square(
+                    &mut builder,
+                    i as f32,
+                    i as f32 + 2.,
+                    /*clockwise=*/ i % 2 == 0,
+                );
+                let color = LinSrgba::new(i as f32 / 32., 0., 0., 1.);
+                (
+                    builder.build(),
+                    Method::Fill(FillRule::EvenOdd),
+                    color,
+                    DEFAULT_TOLERANCE,
+                )
+            })
+        };
+
+        let (vertices, indices) =
+            raster_paths(paths().collect(), Some(viewport(64.))).expect("to raster paths");
+
+        let (expected_vertices, expected_indices) = paths().fold(
+            (vec![], vec![]),
+            |(mut vertices, mut indices): (Vec<GpuVertex>, Vec<u32>),
+             (path, method, color, tolerance)| {
+                let (mut new_vertices, new_indices) =
+                    raster_path(&path, method, color, tolerance).expect("to raster path");
+                let idx = vertices.len() as u32;
+                vertices.append(&mut new_vertices);
+                indices.extend(new_indices.into_iter().map(|i| i + idx));
+                (vertices, indices)
+            },
+        );
+
+        assert_eq!(indices, expected_indices);
+        assert_eq!(vertices.len(), expected_vertices.len());
+        vertices
+            .iter()
+            .zip(expected_vertices.iter())
+            .for_each(|(v, expected)| {
+                assert_eq!(v.vpos, expected.vpos);
+                assert_eq!(v.vcol, expected.vcol);
+            });
+    }
+
+    #[test]
+    fn tolerance() {
+        let blob = |tolerance| {
+            let mut builder = Builder::new();
+            builder.move_to(P2::new(0., 0.));
+            builder.cubic_bezier_to(P2::new(0., 100.), P2::new(100., 100.), P2::new(100., 0.));
+            builder.cubic_bezier_to(P2::new(100., -50.), P2::new(0., -50.), P2::new(0., 0.));
+            builder.close();
+            raster_path(
+                &builder.build(),
+                Method::Fill(FillRule::EvenOdd),
+                LinSrgba::new(1., 1., 1., 1.),
+                tolerance,
+            )
+            .expect("to raster blob")
+        };
+
+        let (fine, _) = blob(0.01);
+        let (coarse, _) = blob(1.);
+        assert!(fine.len() > coarse.len());
+
+        let (fine_min, fine_max) = bounds(&fine);
+        let (coarse_min, coarse_max) = bounds(&coarse);
+        assert!((fine_min - coarse_min).length() < 1.);
+        assert!((fine_max - coarse_max).length() < 1.);
+        assert!((fine_max.y - 75.).abs() < 0.01);
+    }
+}
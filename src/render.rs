@@ -27,6 +27,8 @@ pub struct Context<'a> {
     /// The current frame in the composition.
     pub frame: usize,
     /// The elapsed time in the composition.
+    ///
+    /// Shaders can read the same time, in seconds, from the `_valora_time` uniform.
     pub time: Duration,
 }
 
@@ -94,19 +96,18 @@ impl<'a, F1: Fn() -> Frame + 'a, F2: Fn(usize, u64) -> PathBuf> Renderer<'a, F1,
             }
         }) {
             let mut canvas = Canvas::new(default_shader.clone(), self.options.world.scale);
+            let time = Duration::from_secs_f32(frame as f32 / self.options.world.framerate as f32);
             f(
                 Context {
                     rng: self.rng,
                     world: self.options.world,
                     frame,
-                    time: Duration::from_secs_f32(
-                        frame as f32 / self.options.world.framerate as f32,
-                    ),
+                    time,
                 },
                 &mut canvas,
             );
 
-            let updates = self.render_frame(self.options.world.seed, frame, canvas)?;
+            let updates = self.render_frame(self.options.world.seed, frame, time, canvas)?;
             if updates.should_quit {
                 return Ok(RenderReport {
                     explicit_quit: true,
@@ -136,6 +137,7 @@ impl<'a, F1: Fn() -> Frame + 'a, F2: Fn(usize, u64) -> PathBuf> Renderer<'a, F1,
         &mut self,
         current_seed: u64,
         frame_number: usize,
+        time: Duration,
         canvas: Canvas,
     ) -> Result<FrameUpdates> {
         match self.strategy {
@@ -149,6 +151,7 @@ impl<'a, F1: Fn() -> Frame + 'a, F2: Fn(usize, u64) -> PathBuf> Renderer<'a, F1,
                 self.gpu.render(
                     self.output_width,
                     self.output_height,
+                    time,
                     canvas,
                     &mut buffer.as_surface(),
                 )?;
@@ -178,6 +181,7 @@ impl<'a, F1: Fn() -> Frame + 'a, F2: Fn(usize, u64) -> PathBuf> Renderer<'a, F1,
                             )
                         },
                     },
+                    /*custom_vertex=*/ false,
                 );
                 let mut quad_canvas = Canvas::new(shader.clone(), self.options.world.scale);
                 quad_canvas.paint(Filled(self.options.world));
@@ -188,6 +192,7 @@ impl<'a, F1: Fn() -> Frame + 'a, F2: Fn(usize, u64) -> PathBuf> Renderer<'a, F1,
                 self.gpu.render(
                     self.output_width,
                     self.output_height,
+                    time,
                     quad_canvas,
                     &mut frame,
                 )?;
@@ -244,6 +249,7 @@ impl<'a, F1: Fn() -> Frame + 'a, F2: Fn(usize, u64) -> PathBuf> Renderer<'a, F1,
                 self.gpu.render(
                     self.output_width,
                     self.output_height,
+                    time,
                     canvas,
                     &mut buffer.as_surface(),
                 )?;
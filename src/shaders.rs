@@ -1,19 +1,30 @@
 //! Fragment shader API.
 
 use crate::{
-    gpu::{Gpu, Shader},
-    uniforms::OwnedUniforms,
+    gpu::{Gpu, Shader, UniformBuffer},
     Result,
 };
 use glium::Program;
 use glslwatch::GLSLTree;
-use std::{path::Path, rc::Rc};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{Hash, Hasher},
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// How long the glsl source must go unchanged after an edit before the program is recompiled,
+/// so that editors which write a file in several steps trigger only one reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(100);
 
 /// A dynamically reloaded GLSL program.
 pub struct ShaderProgram {
     tree: GLSLTree,
+    vertex_tree: Option<GLSLTree>,
     gpu: Gpu,
-    last_program: Option<Rc<Program>>,
+    reload: Reload<Rc<Program>>,
     last_shader: Shader,
 }
 
@@ -34,13 +45,35 @@ impl ShaderProgram {
         Ok(Self {
             gpu: gpu.clone(),
             tree: GLSLTree::new(glsl, include_directories)?,
+            vertex_tree: None,
             last_shader: gpu.default_shader(),
-            last_program: None,
+            reload: Reload::default(),
+        })
+    }
+
+    /// Creates a dynamically reloaded glsl program from the given fragment shader path, with a
+    /// dynamically reloaded vertex shader to run in place of the default one.
+    ///
+    /// The vertex shader may only take the `vec2 vpos` and `vec4 vcol` inputs. It receives the
+    /// same `_valora_width`, `_valora_height` and `_valora_height_sign` uniforms as the default
+    /// vertex shader (see `shaders/default.vert`) to project positions into clip space.
+    pub fn new_with_vertex_shader<S: AsRef<Path>>(
+        gpu: &Gpu,
+        vertex_glsl: impl AsRef<Path>,
+        fragment_glsl: impl AsRef<Path>,
+        include_directories: &[S],
+    ) -> Result<Self> {
+        Ok(Self {
+            vertex_tree: Some(GLSLTree::new(vertex_glsl, include_directories)?),
+            ..Self::new_with_include_paths(gpu, fragment_glsl, include_directories)?
         })
     }
 
     /// Create a usable shader by binding uniform values.
-    pub fn bind(&mut self, uniforms: impl OwnedUniforms + 'static) -> Shader {
+    ///
+    /// Uniforms may be any `OwnedUniforms` type, such as a `#[derive(UniformSet)]` struct, or a
+    /// `UniformBuffer` made with `UniformBuffer::builder()`.
+    pub fn bind(&mut self, uniforms: impl Into<UniformBuffer>) -> Shader {
         match self.try_bind(uniforms) {
             Ok(shader) => shader,
             Err(e) => {
@@ -50,24 +83,264 @@ impl ShaderProgram {
         }
     }
 
-    pub fn try_bind(&mut self, uniforms: impl OwnedUniforms + 'static) -> Result<Shader> {
-        let program = if self.tree.expired()? {
-            let mut new_tree = self.tree.clone().refresh()?;
-            std::mem::swap(&mut self.tree, &mut new_tree);
-            let src = self.tree.render();
-            let program = self.gpu.compile_glsl(src)?;
-            self.last_program = Some(program.clone());
-            program
-        } else if let Some(program) = self.last_program.as_ref() {
-            program.clone()
-        } else {
-            let src = self.tree.render();
-            let program = self.gpu.compile_glsl(src)?;
-            self.last_program = Some(program.clone());
-            program
-        };
+    pub fn try_bind(&mut self, uniforms: impl Into<UniformBuffer>) -> Result<Shader> {
+        let changed = self.changed_source()?;
+        let reload = self.reload.settled(changed, Instant::now());
+        if reload {
+            self.tree = self.tree.clone().refresh()?;
+            if let Some(vertex_tree) = self.vertex_tree.as_mut() {
+                *vertex_tree = vertex_tree.clone().refresh()?;
+            }
+        }
 
-        self.last_shader = self.gpu.build_shader(program, uniforms);
+        let (gpu, tree, vertex_tree) = (&self.gpu, &self.tree, self.vertex_tree.as_ref());
+        let program = self.reload.program(reload, || match vertex_tree {
+            Some(vertex_tree) => {
+                gpu.compile_glsl_with_vertex_shader(vertex_tree.render(), tree.render())
+            }
+            None => gpu.compile_glsl(tree.render()),
+        })?;
+
+        self.last_shader = self
+            .gpu
+            .build_shader(program, uniforms, self.vertex_tree.is_some());
         Ok(self.last_shader.clone())
     }
+
+    /// Returns a hash of the glsl source as it is now on disk, or `None` if no file has changed
+    /// since the program was last compiled.
+    fn changed_source(&self) -> Result<Option<u64>> {
+        let vertex_expired = match self.vertex_tree.as_ref() {
+            Some(vertex_tree) => vertex_tree.expired()?,
+            None => false,
+        };
+        if !self.tree.expired()? && !vertex_expired {
+            return Ok(None);
+        }
+
+        let mut hasher = DefaultHasher::new();
+        self.tree.clone().refresh()?.render().hash(&mut hasher);
+        if let Some(vertex_tree) = self.vertex_tree.as_ref() {
+            vertex_tree.clone().refresh()?.render().hash(&mut hasher);
+        }
+        Ok(Some(hasher.finish()))
+    }
+}
+
+/// The last program to compile, and any edit to its source which is waiting to settle.
+#[derive(Debug)]
+struct Reload<P> {
+    program: Option<P>,
+    /// The hash of the edited source and when it was first seen.
+    pending: Option<(u64, Instant)>,
+}
+
+impl<P> Default for Reload<P> {
+    fn default() -> Self {
+        Self {
+            program: None,
+            pending: None,
+        }
+    }
+}
+
+impl<P: Clone> Reload<P> {
+    /// Takes the hash of the changed source, if it has changed, and returns true once it has gone
+    /// `RELOAD_DEBOUNCE` without changing again.
+    fn settled(&mut self, changed: Option<u64>, now: Instant) -> bool {
+        match (changed, self.pending) {
+            (None, _) => {
+                self.pending = None;
+                false
+            }
+            (Some(hash), Some((pending, since))) if hash == pending => {
+                if now.duration_since(since) < RELOAD_DEBOUNCE {
+                    return false;
+                }
+                self.pending = None;
+                true
+            }
+            (Some(hash), _) => {
+                self.pending = Some((hash, now));
+                false
+            }
+        }
+    }
+
+    /// Returns the program to draw with, compiling a new one if there is none yet or `reload` is
+    /// true. If compilation fails, the previous program is kept for later calls.
+    fn program<E>(
+        &mut self,
+        reload: bool,
+        compile: impl FnOnce() -> std::result::Result<P, E>,
+    ) -> std::result::Result<P, E> {
+        match self.program.as_ref() {
+            Some(program) if !reload => Ok(program.clone()),
+            _ => {
+                let program = compile()?;
+                self.program = Some(program.clone());
+                Ok(program)
+            }
+        }
+    }
+}
+
+/// A GLSL compilation error, with the source lines around the line the driver reported.
+///
+/// This implements `std::error::Error`, and so converts into `failure::Error` as well.
+#[derive(Debug, Clone)]
+pub struct ShaderError {
+    /// The driver's error log.
+    pub message: String,
+    /// The 1-based source line of the first error in the log, if one could be found.
+    pub line: Option<usize>,
+    /// The source lines around `line`, with the offending line marked.
+    pub context: String,
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n{}", self.message, self.context)
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl ShaderError {
+    const CONTEXT_LINES: usize = 2;
+
+    /// Builds an error from the driver's log for the given source.
+    ///
+    /// Drivers report locations as `0:12(3)` (Mesa), `0(12)` (Nvidia) or `0:12:` (others);
+    /// in each the second number is the line.
+    pub fn from_log(source: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let line = Self::find_line(&message);
+        let context = line
+            .map(|line| {
+                let first = line.saturating_sub(Self::CONTEXT_LINES).max(1);
+                source
+                    .lines()
+                    .enumerate()
+                    .map(|(i, text)| (i + 1, text))
+                    .skip(first - 1)
+                    .take_while(|(n, _)| *n <= line + Self::CONTEXT_LINES)
+                    .map(|(n, text)| {
+                        let marker = if n == line { ">" } else { " " };
+                        format!("{} {:>4} | {}", marker, n, text)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+
+        Self {
+            message,
+            line,
+            context,
+        }
+    }
+
+    fn find_line(log: &str) -> Option<usize> {
+        let bytes = log.as_bytes();
+        let digits_from = |start: usize| {
+            bytes[start..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+        };
+
+        let mut i = 0;
+        while i < bytes.len() {
+            let file_digits = digits_from(i);
+            if file_digits == 0 {
+                i += 1;
+                continue;
+            }
+
+            let separator = i + file_digits;
+            if let Some(b':') | Some(b'(') = bytes.get(separator) {
+                let line_digits = digits_from(separator + 1);
+                if line_digits > 0 {
+                    let line = &log[separator + 1..separator + 1 + line_digits];
+                    return line.parse().ok();
+                }
+            }
+            i = separator;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BROKEN: &str = "#version 330\n\
+                          out vec4 frag;\n\
+                          void main() {\n\
+                          \x20   frag = vec4(1.0) +;\n\
+                          }\n";
+
+    #[test]
+    fn shader_error_context() {
+        for log in &[
+            "0:4(24): error: syntax error, unexpected ';'",
+            "0(4) : error C0000: syntax error, unexpected ';'",
+            "ERROR: 0:4: '' : syntax error",
+        ] {
+            let error = ShaderError::from_log(BROKEN, *log);
+            assert_eq!(error.line, Some(4));
+            assert!(error.context.contains(">    4 |     frag = vec4(1.0) +;"));
+            assert!(error.context.contains("     2 | out vec4 frag;"));
+            assert!(error.to_string().starts_with(log));
+        }
+    }
+
+    #[test]
+    fn shader_error_without_line() {
+        let error = ShaderError::from_log(BROKEN, "link error");
+        assert_eq!(error.line, None);
+        assert_eq!(error.context, "");
+    }
+
+    #[test]
+    fn reload_waits_for_edits_to_settle() {
+        let mut reload = Reload::<u32>::default();
+        let start = Instant::now();
+        let after = |millis| start + Duration::from_millis(millis);
+
+        assert!(!reload.settled(None, start));
+        assert!(!reload.settled(Some(1), after(0)));
+        assert!(!reload.settled(Some(1), after(60)));
+        // A second save restarts the wait.
+        assert!(!reload.settled(Some(2), after(90)));
+        assert!(!reload.settled(Some(2), after(150)));
+        assert!(reload.settled(Some(2), after(190)));
+        assert!(!reload.settled(None, after(200)));
+    }
+
+    #[test]
+    fn reload_recompiles_and_keeps_last_program_on_error() {
+        let mut reload = Reload::<u32>::default();
+        assert_eq!(reload.program(false, || Ok::<_, ()>(1)), Ok(1));
+        assert_eq!(reload.program(false, || Ok::<_, ()>(2)), Ok(1));
+        assert_eq!(reload.program(true, || Ok::<_, ()>(3)), Ok(3));
+
+        assert_eq!(
+            reload.program(true, || Err("syntax error")),
+            Err("syntax error")
+        );
+        assert_eq!(reload.program(false, || Err("syntax error")), Ok(3));
+    }
+
+    #[test]
+    fn shader_error_is_std_error() {
+        let error: Box<dyn std::error::Error> = Box::new(ShaderError::from_log(BROKEN, "0:4(24)"));
+        assert!(error.to_string().contains(">    4 |"));
+
+        let error = failure::Error::from(ShaderError::from_log(BROKEN, "link error"));
+        assert!(error.downcast_ref::<ShaderError>().is_some());
+    }
 }